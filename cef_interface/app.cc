@@ -1,5 +1,7 @@
 #include "app.hh"
 
+#include <cstdlib>
+
 // Minimal implementation of CefApp for the browser process.
 
 MyApp::MyApp(Callbacks callbacks) {
@@ -22,6 +24,12 @@ void MyApp::OnBeforeCommandLineProcessing(
   command_line->AppendSwitchWithValue("autoplay-policy",
                                       "no-user-gesture-required");
   command_line->AppendSwitch("disable-extensions");
+
+  // set by the tests, there's no gpu in ci
+  if (getenv("CEF_HEADLESS")) {
+    command_line->AppendSwitch("disable-gpu");
+    command_line->AppendSwitch("disable-gpu-compositing");
+  }
 }
 
 // CefBrowserProcessHandler methods:
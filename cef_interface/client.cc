@@ -1,5 +1,7 @@
 #include "client.hh"
 
+#include <vector>
+
 MyClient::MyClient(Callbacks callbacks) {
   this->on_before_close_callback = callbacks.on_before_close_callback;
   this->on_paint_callback = callbacks.on_paint_callback;
@@ -7,6 +9,11 @@ MyClient::MyClient(Callbacks callbacks) {
   this->on_after_created_callback = callbacks.on_after_created_callback;
   this->on_title_change_callback = callbacks.on_title_change_callback;
   this->get_view_rect_callback = callbacks.get_view_rect_callback;
+  this->on_console_message_callback = callbacks.on_console_message_callback;
+  this->on_before_browse_callback = callbacks.on_before_browse_callback;
+  this->get_scale_factor_callback = callbacks.get_scale_factor_callback;
+  this->on_before_resource_load_callback =
+      callbacks.on_before_resource_load_callback;
 }
 
 // CefClient methods:
@@ -48,6 +55,20 @@ void MyClient::OnLoadingProgressChange(CefRefPtr<CefBrowser> browser,
   // rust_print(ag.c_str());
 }
 
+bool MyClient::OnConsoleMessage(CefRefPtr<CefBrowser> browser,
+                                cef_log_severity_t level,
+                                const CefString& message,
+                                const CefString& source,
+                                int line) {
+  if (on_console_message_callback) {
+    auto message_utf8 = message.ToString();
+    return on_console_message_callback(
+        cef_interface_add_ref_browser(browser.get()), message_utf8.c_str());
+  }
+
+  return false;
+}
+
 // CefLifeSpanHandler methods:
 void MyClient::OnBeforeClose(CefRefPtr<CefBrowser> browser) {
   if (on_before_close_callback) {
@@ -98,6 +119,23 @@ void MyClient::GetViewRect(CefRefPtr<CefBrowser> browser, CefRect& rect) {
   rect.height = new_rect.height;
 }
 
+bool MyClient::GetScreenInfo(CefRefPtr<CefBrowser> browser,
+                             CefScreenInfo& screen_info) {
+  if (!get_scale_factor_callback) {
+    return false;
+  }
+
+  CefRect view_rect;
+  GetViewRect(browser, view_rect);
+
+  screen_info.device_scale_factor =
+      get_scale_factor_callback(cef_interface_add_ref_browser(browser.get()));
+  screen_info.rect = view_rect;
+  screen_info.available_rect = view_rect;
+
+  return true;
+}
+
 void MyClient::OnPaint(CefRefPtr<CefBrowser> browser,
                        CefRenderHandler::PaintElementType type,
                        const CefRenderHandler::RectList& dirtyRects,
@@ -105,8 +143,19 @@ void MyClient::OnPaint(CefRefPtr<CefBrowser> browser,
                        int width,
                        int height) {
   if (on_paint_callback) {
-    on_paint_callback(cef_interface_add_ref_browser(browser.get()), pixels,
-                      width, height);
+    std::vector<RustRect> rects;
+    rects.reserve(dirtyRects.size());
+    for (auto& dirty_rect : dirtyRects) {
+      RustRect rect;
+      rect.x = dirty_rect.x;
+      rect.y = dirty_rect.y;
+      rect.width = dirty_rect.width;
+      rect.height = dirty_rect.height;
+      rects.push_back(rect);
+    }
+
+    on_paint_callback(cef_interface_add_ref_browser(browser.get()),
+                      rects.data(), (int)rects.size(), pixels, width, height);
   }
 }
 
@@ -122,6 +171,21 @@ void MyClient::OnLoadEnd(CefRefPtr<CefBrowser> browser,
 }
 
 // CefRequestHandler methods:
+bool MyClient::OnBeforeBrowse(CefRefPtr<CefBrowser> browser,
+                              CefRefPtr<CefFrame> frame,
+                              CefRefPtr<CefRequest> request,
+                              bool user_gesture,
+                              bool is_redirect) {
+  if (on_before_browse_callback) {
+    std::string url = request->GetURL();
+    return on_before_browse_callback(
+        cef_interface_add_ref_browser(browser.get()), url.c_str(),
+        frame->IsMain());
+  }
+
+  return false;
+}
+
 CefRefPtr<CefResourceRequestHandler> MyClient::GetResourceRequestHandler(
     CefRefPtr<CefBrowser> browser,
     CefRefPtr<CefFrame> frame,
@@ -130,32 +194,52 @@ CefRefPtr<CefResourceRequestHandler> MyClient::GetResourceRequestHandler(
     bool is_download,
     const CefString& request_initiator,
     bool& disable_default_handling) {
+  // every request is checked in OnBeforeResourceLoad
+  return this;
+}
+
+// CefResourceRequestHandler methods:
+CefResourceRequestHandler::ReturnValue MyClient::OnBeforeResourceLoad(
+    CefRefPtr<CefBrowser> browser,
+    CefRefPtr<CefFrame> frame,
+    CefRefPtr<CefRequest> request,
+    CefRefPtr<CefRequestCallback> callback) {
+  std::string url = request->GetURL();
+
   auto referrer_url = request->GetReferrerURL();
-  if (!referrer_url.c_str()) {
-    std::string url = request->GetURL();
+  if (frame && !referrer_url.c_str()) {
     auto main_url = frame->GetURL();
 
     if (main_url == "" && url.rfind("https://www.youtube.com/embed/", 0) == 0) {
-      return this;
+      // fix for some embedded youtube videos giving "video unavailable"
+      // something to do with referrer not being set from our data: url
+      auto new_referrer_url = L"https://www.youtube.com/";
+      request->SetReferrer(new_referrer_url,
+                           CefRequest::ReferrerPolicy::REFERRER_POLICY_DEFAULT);
     }
   }
 
-  return NULL;
+  // service workers have no browser
+  if (!on_before_resource_load_callback || !browser) {
+    return CefResourceRequestHandler::ReturnValue::RV_CONTINUE;
+  }
+
+  // we're on the IO thread, rust lives on the UI thread;
+  // this is called again for the new url after a redirect
+  CefPostTask(TID_UI, base::Bind(&MyClient::CheckResourceLoad, this, browser,
+                                 url, callback));
+
+  return CefResourceRequestHandler::ReturnValue::RV_CONTINUE_ASYNC;
 }
 
-// CefResourceRequestHandler methods:
-CefResourceRequestHandler::ReturnValue MyClient::OnBeforeResourceLoad(
-    CefRefPtr<CefBrowser> browser,
-    CefRefPtr<CefFrame> frame,
-    CefRefPtr<CefRequest> request,
-    CefRefPtr<CefRequestCallback> callback) {
-  // fix for some embedded youtube videos giving "video unavailable"
-  // something to do with referrer not being set from our data: url
-  auto new_referrer_url = L"https://www.youtube.com/";
-  request->SetReferrer(new_referrer_url,
-                       CefRequest::ReferrerPolicy::REFERRER_POLICY_DEFAULT);
+void MyClient::CheckResourceLoad(CefRefPtr<CefBrowser> browser,
+                                 std::string url,
+                                 CefRefPtr<CefRequestCallback> callback) {
+  CEF_REQUIRE_UI_THREAD();
 
-  return CefResourceRequestHandler::ReturnValue::RV_CONTINUE;
+  on_before_resource_load_callback(
+      cef_interface_add_ref_browser(browser.get()), url.c_str(),
+      cef_interface_add_ref_request_callback(callback.get()));
 }
 
 // CefJSDialogHandler methods:
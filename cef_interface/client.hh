@@ -1,6 +1,8 @@
 #pragma once
 
+#include <include/base/cef_bind.h>
 #include <include/cef_client.h>
+#include <include/wrapper/cef_closure_task.h>
 #include <include/wrapper/cef_helpers.h>
 
 #include <unordered_map>
@@ -37,6 +39,12 @@ class MyClient : public CefClient,
   void OnLoadingProgressChange(CefRefPtr<CefBrowser> browser,
                                double progress) OVERRIDE;
 
+  bool OnConsoleMessage(CefRefPtr<CefBrowser> browser,
+                        cef_log_severity_t level,
+                        const CefString& message,
+                        const CefString& source,
+                        int line) OVERRIDE;
+
   // CefLifeSpanHandler methods:
   bool DoClose(CefRefPtr<CefBrowser> browser) OVERRIDE;
   void OnAfterCreated(CefRefPtr<CefBrowser> browser) OVERRIDE;
@@ -57,6 +65,8 @@ class MyClient : public CefClient,
 
   // CefRenderHandler methods:
   void GetViewRect(CefRefPtr<CefBrowser> browser, CefRect& rect) OVERRIDE;
+  bool GetScreenInfo(CefRefPtr<CefBrowser> browser,
+                     CefScreenInfo& screen_info) OVERRIDE;
   void OnPaint(CefRefPtr<CefBrowser> browser,
                CefRenderHandler::PaintElementType type,
                const CefRenderHandler::RectList& dirtyRects,
@@ -70,6 +80,12 @@ class MyClient : public CefClient,
                  int httpStatusCode) OVERRIDE;
 
   // CefRequestHandler methods:
+  bool OnBeforeBrowse(CefRefPtr<CefBrowser> browser,
+                      CefRefPtr<CefFrame> frame,
+                      CefRefPtr<CefRequest> request,
+                      bool user_gesture,
+                      bool is_redirect) OVERRIDE;
+
   CefRefPtr<CefResourceRequestHandler> GetResourceRequestHandler(
       CefRefPtr<CefBrowser> browser,
       CefRefPtr<CefFrame> frame,
@@ -86,6 +102,10 @@ class MyClient : public CefClient,
       CefRefPtr<CefRequest> request,
       CefRefPtr<CefRequestCallback> callback) OVERRIDE;
 
+  void CheckResourceLoad(CefRefPtr<CefBrowser> browser,
+                         std::string url,
+                         CefRefPtr<CefRequestCallback> callback);
+
   // CefJSDialogHandler methods:
   bool OnJSDialog(CefRefPtr<CefBrowser> browser,
                   const CefString& origin_url,
@@ -117,6 +137,10 @@ class MyClient : public CefClient,
   OnAfterCreatedCallback on_after_created_callback;
   OnTitleChangeCallback on_title_change_callback;
   GetViewRectCallback get_view_rect_callback;
+  OnConsoleMessageCallback on_console_message_callback;
+  OnBeforeBrowseCallback on_before_browse_callback;
+  GetScaleFactorCallback get_scale_factor_callback;
+  OnBeforeResourceLoadCallback on_before_resource_load_callback;
 
   IMPLEMENT_REFCOUNTING(MyClient);
   DISALLOW_COPY_AND_ASSIGN(MyClient);
@@ -45,6 +45,20 @@ extern "C" int cef_interface_release_ref_browser(CefBrowser* browser_ptr) {
   return 0;
 }
 
+extern "C" RustRefRequestCallback cef_interface_add_ref_request_callback(
+    CefRequestCallback* ptr) {
+  ptr->AddRef();
+
+  RustRefRequestCallback r;
+  r.ptr = ptr;
+  return r;
+}
+extern "C" int cef_interface_release_ref_request_callback(
+    CefRequestCallback* callback_ptr) {
+  callback_ptr->Release();
+  return 0;
+}
+
 extern "C" RustRefApp cef_interface_create_app(Callbacks callbacks) {
   CefRefPtr<MyApp> app = new MyApp(callbacks);
 
@@ -179,11 +193,44 @@ extern "C" int cef_interface_browser_reload(CefBrowser* browser_ptr) {
   return 0;
 }
 
+extern "C" int cef_interface_request_callback_continue(
+    CefRequestCallback* callback_ptr,
+    bool allow) {
+  callback_ptr->Continue(allow);
+  return 0;
+}
+
 extern "C" int cef_interface_browser_was_resized(CefBrowser* browser_ptr) {
   browser_ptr->GetHost()->WasResized();
   return 0;
 }
 
+extern "C" int cef_interface_browser_notify_screen_info_changed(
+    CefBrowser* browser_ptr) {
+  // picks up a new scale factor from GetScreenInfo and repaints
+  browser_ptr->GetHost()->NotifyScreenInfoChanged();
+  return 0;
+}
+
+extern "C" int cef_interface_browser_invalidate(CefBrowser* browser_ptr) {
+  // repaints the whole view
+  browser_ptr->GetHost()->Invalidate(PET_VIEW);
+  return 0;
+}
+
+extern "C" int cef_interface_browser_was_hidden(CefBrowser* browser_ptr,
+                                                int hidden) {
+  // stops painting but keeps audio playing
+  browser_ptr->GetHost()->WasHidden(hidden != 0);
+  return 0;
+}
+
+extern "C" int cef_interface_browser_set_frame_rate(CefBrowser* browser_ptr,
+                                                    int frame_rate) {
+  browser_ptr->GetHost()->SetWindowlessFrameRate(frame_rate);
+  return 0;
+}
+
 extern "C" int cef_interface_browser_close(CefBrowser* browser_ptr) {
   auto browser_host = browser_ptr->GetHost();
 
@@ -5,6 +5,7 @@
 class MyApp;
 class MyClient;
 class CefBrowser;
+class CefRequestCallback;
 
 struct RustRefApp {
   MyApp* ptr;
@@ -28,6 +29,15 @@ extern "C" RustRefBrowser cef_interface_add_ref_browser(
     CefBrowser* browser_ptr);
 extern "C" int cef_interface_release_ref_browser(CefBrowser* browser_ptr);
 
+struct RustRefRequestCallback {
+  CefRequestCallback* ptr;
+};
+
+extern "C" RustRefRequestCallback cef_interface_add_ref_request_callback(
+    CefRequestCallback* callback_ptr);
+extern "C" int cef_interface_release_ref_request_callback(
+    CefRequestCallback* callback_ptr);
+
 /// Called on the browser process UI thread immediately after the CEF context
 /// has been initialized.
 typedef void (*OnContextInitializedCallback)(RustRefClient client);
@@ -38,7 +48,17 @@ typedef void (*OnAfterCreatedCallback)(RustRefBrowser browser);
 /// Called just before a browser is destroyed.
 typedef void (*OnBeforeCloseCallback)(RustRefBrowser browser);
 
+struct RustRect {
+  int x;
+  int y;
+  int width;
+  int height;
+};
+
+/// `dirty_rects` are the parts of `pixels` that changed since the last paint.
 typedef void (*OnPaintCallback)(RustRefBrowser browser,
+                                const RustRect* dirty_rects,
+                                int dirty_rects_count,
                                 const void* pixels,
                                 int width,
                                 int height);
@@ -50,15 +70,30 @@ typedef void (*OnLoadEndCallback)(RustRefBrowser browser);
 typedef void (*OnTitleChangeCallback)(RustRefBrowser browser,
                                       const char* title);
 
-struct RustRect {
-  int x;
-  int y;
-  int width;
-  int height;
-};
-
 typedef RustRect (*GetViewRectCallback)(RustRefBrowser browser);
 
+/// Pixels painted per view pixel, lower paints smaller frames
+/// without changing the page's layout.
+typedef float (*GetScaleFactorCallback)(RustRefBrowser browser);
+
+/// Called when the page logs a console message.
+/// Return true to stop the message from being output to the console.
+typedef bool (*OnConsoleMessageCallback)(RustRefBrowser browser,
+                                         const char* message);
+
+/// Called before a frame navigates, including redirects.
+/// Return true to cancel the navigation.
+typedef bool (*OnBeforeBrowseCallback)(RustRefBrowser browser,
+                                       const char* url,
+                                       bool is_main_frame);
+
+/// Called on the UI thread before any request is sent, including frames,
+/// subresources and redirects. Answer with
+/// `cef_interface_request_callback_continue`, now or later.
+typedef void (*OnBeforeResourceLoadCallback)(RustRefBrowser browser,
+                                             const char* url,
+                                             RustRefRequestCallback callback);
+
 struct Callbacks {
   OnContextInitializedCallback on_context_initialized_callback;
   OnAfterCreatedCallback on_after_created_callback;
@@ -67,6 +102,10 @@ struct Callbacks {
   OnLoadEndCallback on_load_end_callback;
   OnTitleChangeCallback on_title_change_callback;
   GetViewRectCallback get_view_rect_callback;
+  OnConsoleMessageCallback on_console_message_callback;
+  OnBeforeBrowseCallback on_before_browse_callback;
+  GetScaleFactorCallback get_scale_factor_callback;
+  OnBeforeResourceLoadCallback on_before_resource_load_callback;
 };
 
 // functions to rust
@@ -94,7 +133,18 @@ extern "C" int cef_interface_browser_send_text(CefBrowser* browser_ptr,
                                                const char* text);
 extern "C" int cef_interface_browser_reload(CefBrowser* browser_ptr);
 
+extern "C" int cef_interface_request_callback_continue(
+    CefRequestCallback* callback_ptr,
+    bool allow);
+
 extern "C" int cef_interface_browser_was_resized(CefBrowser* browser_ptr);
+extern "C" int cef_interface_browser_notify_screen_info_changed(
+    CefBrowser* browser_ptr);
+extern "C" int cef_interface_browser_invalidate(CefBrowser* browser_ptr);
+extern "C" int cef_interface_browser_was_hidden(CefBrowser* browser_ptr,
+                                                int hidden);
+extern "C" int cef_interface_browser_set_frame_rate(CefBrowser* browser_ptr,
+                                                    int frame_rate);
 
 /// Tell browser to close, OnBeforeClose will be called soon
 extern "C" int cef_interface_browser_close(CefBrowser* browser_ptr);
@@ -1,3 +1,4 @@
+use crate::metrics::TaskGuard;
 use async_dispatcher::{Dispatcher, DispatcherHandle, LocalDispatcherHandle};
 use classicube_helpers::{tick::TickEventHandler, OptionWithInner};
 use lazy_static::lazy_static;
@@ -36,6 +37,20 @@ impl AsyncManager {
     pub fn initialize(&mut self) {
         debug!("initialize async_manager");
 
+        Self::initialize_executors();
+
+        self.tick_handler.on(|_task| {
+            Self::step();
+        });
+    }
+
+    pub fn shutdown(&mut self) {
+        Self::shutdown_executors();
+    }
+
+    /// Starts our executors without hooking the game's tick,
+    /// for tests that call `step()` themselves
+    pub fn initialize_executors() {
         let async_dispatcher = Dispatcher::new();
         *ASYNC_DISPATCHER_HANDLE.lock().unwrap() = Some(async_dispatcher.get_handle());
         ASYNC_DISPATCHER_LOCAL_HANDLE.with(|cell| {
@@ -52,13 +67,9 @@ impl AsyncManager {
             .unwrap();
 
         *TOKIO_RUNTIME.lock().unwrap() = Some(rt);
-
-        self.tick_handler.on(|_task| {
-            Self::step();
-        });
     }
 
-    pub fn shutdown(&mut self) {
+    pub fn shutdown_executors() {
         {
             let mut option = TOKIO_RUNTIME.lock().unwrap();
             if option.is_some() {
@@ -141,7 +152,14 @@ impl AsyncManager {
         F: Future + Send + 'static,
         F::Output: Send + 'static,
     {
-        TOKIO_RUNTIME.with_inner(|rt| rt.spawn(f)).unwrap()
+        TOKIO_RUNTIME
+            .with_inner(|rt| {
+                rt.spawn(async move {
+                    let _guard = TaskGuard::new();
+                    f.await
+                })
+            })
+            .unwrap()
     }
 
     #[allow(dead_code)]
@@ -154,7 +172,10 @@ impl AsyncManager {
             handle.as_mut().expect("handle.as_mut()").clone()
         };
 
-        handle.spawn(f);
+        handle.spawn(async move {
+            let _guard = TaskGuard::new();
+            f.await
+        });
     }
 
     #[allow(dead_code)]
@@ -179,6 +200,9 @@ impl AsyncManager {
             .with_inner(|handle| handle.clone())
             .expect("ASYNC_DISPATCHER_LOCAL_HANDLE is None");
 
-        handle.spawn(f);
+        handle.spawn(async move {
+            let _guard = TaskGuard::new();
+            f.await
+        });
     }
 }
@@ -6,6 +6,7 @@ include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
 use crate::error::*;
 use log::debug;
+use serde::de::DeserializeOwned;
 use std::{
     ffi::{CStr, CString},
     os::raw::c_int,
@@ -103,10 +104,23 @@ impl RustRefBrowser {
 
     pub fn execute_javascript<T: Into<Vec<u8>>>(&self, code: T) -> Result<()> {
         let code = CString::new(code).unwrap();
+        crate::metrics::record_js_call();
 
         to_result(unsafe { cef_interface_browser_execute_javascript(self.get(), code.as_ptr()) })
     }
 
+    /// Runs `code` in the page and resolves with its JSON-decoded result
+    ///
+    /// If `code` returns a Promise, its resolved value is used.
+    pub async fn eval<T: DeserializeOwned>(&self, code: &str) -> Result<T> {
+        super::javascript::eval(self, code).await
+    }
+
+    /// Resolves with the next frame the browser paints, in RGBA
+    pub async fn capture(&self) -> Result<super::Capture> {
+        super::capture::capture(self).await
+    }
+
     pub fn send_click(&self, x: c_int, y: c_int) -> Result<()> {
         to_result(unsafe { cef_interface_browser_send_click(self.get(), x, y) })
     }
@@ -128,6 +142,22 @@ impl RustRefBrowser {
         to_result(unsafe { cef_interface_browser_was_resized(self.get()) })
     }
 
+    pub fn notify_screen_info_changed(&self) -> Result<()> {
+        to_result(unsafe { cef_interface_browser_notify_screen_info_changed(self.get()) })
+    }
+
+    pub fn invalidate(&self) -> Result<()> {
+        to_result(unsafe { cef_interface_browser_invalidate(self.get()) })
+    }
+
+    pub fn was_hidden(&self, hidden: bool) -> Result<()> {
+        to_result(unsafe { cef_interface_browser_was_hidden(self.get(), hidden as _) })
+    }
+
+    pub fn set_frame_rate(&self, frame_rate: c_int) -> Result<()> {
+        to_result(unsafe { cef_interface_browser_set_frame_rate(self.get(), frame_rate) })
+    }
+
     fn get(&self) -> *mut CefBrowser {
         self.ptr
     }
@@ -142,3 +172,19 @@ impl Clone for RustRefBrowser {
         unsafe { cef_interface_add_ref_browser(self.get()) }
     }
 }
+
+impl RustRefRequestCallback {
+    /// Lets the request go on or cancels it
+    pub fn answer(&self, allow: bool) -> Result<()> {
+        to_result(unsafe { cef_interface_request_callback_continue(self.get(), allow) })
+    }
+
+    fn get(&self) -> *mut CefRequestCallback {
+        self.ptr
+    }
+}
+impl Drop for RustRefRequestCallback {
+    fn drop(&mut self) {
+        to_result(unsafe { cef_interface_release_ref_request_callback(self.get()) }).unwrap();
+    }
+}
@@ -1,10 +1,18 @@
-use super::{bindings::RustRect, CefEvent, CEF_DEFAULT_HEIGHT, CEF_DEFAULT_WIDTH, EVENT_QUEUE};
-use crate::cef::RustRefBrowser;
+use super::{
+    bindings::{RustRect, RustRefRequestCallback},
+    CefEvent, CEF_DEFAULT_HEIGHT, CEF_DEFAULT_WIDTH, EVENT_QUEUE,
+};
+use crate::{
+    async_manager::AsyncManager,
+    cef::RustRefBrowser,
+    metrics,
+    players::policy::{self, Navigation},
+};
 use classicube_helpers::OptionWithInner;
-use log::debug;
+use log::{debug, warn};
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::CStr,
     os::raw::{c_char, c_int},
 };
@@ -14,6 +22,11 @@ thread_local!(
     pub static BROWSERS: RefCell<HashMap<c_int, RustRefBrowser>> = RefCell::new(HashMap::new());
 );
 
+// identifiers of browsers showing one of our media pages
+thread_local!(
+    pub static MEDIA_BROWSERS: RefCell<HashSet<c_int>> = RefCell::new(HashSet::new());
+);
+
 // OnAfterCreated
 pub extern "C" fn on_after_created(browser: RustRefBrowser) {
     let id = browser.get_identifier();
@@ -49,6 +62,16 @@ pub extern "C" fn on_before_close(browser: RustRefBrowser) {
         let browsers = &mut *cell.borrow_mut();
         browsers.remove(&id);
     });
+
+    MEDIA_BROWSERS.with(move |cell| {
+        cell.borrow_mut().remove(&id);
+    });
+
+    BROWSER_SCALES.with(move |cell| {
+        cell.borrow_mut().remove(&id);
+    });
+
+    metrics::remove_browser(id);
 }
 
 // OnPageLoaded
@@ -78,10 +101,125 @@ pub extern "C" fn on_title_change(browser: RustRefBrowser, title_c_str: *const c
         .unwrap();
 }
 
+// OnBeforeBrowse, returns true to cancel
+pub extern "C" fn on_before_browse(
+    browser: RustRefBrowser,
+    url_c_str: *const c_char,
+    is_main_frame: bool,
+) -> bool {
+    let id = browser.get_identifier();
+    let url = unsafe { CStr::from_ptr(url_c_str) }
+        .to_string_lossy()
+        .to_string();
+    let is_media = MEDIA_BROWSERS.with(|cell| cell.borrow().contains(&id));
+
+    match policy::check_navigation(&url, is_media) {
+        Navigation::Allow => false,
+
+        Navigation::Block(reason) => {
+            warn!("blocked {} in browser {}: {}", url, id, reason);
+
+            // a frame inside the page is just left empty
+            if is_main_frame {
+                // cef is still asking us, load after it's done
+                AsyncManager::spawn_local_on_main_thread(async move {
+                    if let Err(e) = browser.load_url(policy::placeholder_url(&url, &reason)) {
+                        warn!("{}", e);
+                    }
+                });
+            }
+
+            true
+        }
+
+        // reloading the page for a frame would restart it, and
+        // on_before_resource_load checks the frame's request anyway
+        Navigation::Resolve(_) if !is_main_frame => false,
+
+        Navigation::Resolve(host) => {
+            debug!("resolving {} before loading it in browser {}", host, id);
+
+            AsyncManager::spawn_local_on_main_thread(async move {
+                policy::resolve(&host).await;
+
+                // checked again now that we know where it goes
+                if let Err(e) = browser.load_url(url) {
+                    warn!("{}", e);
+                }
+            });
+
+            true
+        }
+    }
+}
+
+// OnBeforeResourceLoad, every request a page makes
+pub extern "C" fn on_before_resource_load(
+    browser: RustRefBrowser,
+    url_c_str: *const c_char,
+    callback: RustRefRequestCallback,
+) {
+    let id = browser.get_identifier();
+    let url = unsafe { CStr::from_ptr(url_c_str) }
+        .to_string_lossy()
+        .to_string();
+
+    match policy::check_request(&url) {
+        Navigation::Resolve(host) => {
+            debug!("resolving {} before requesting it in browser {}", host, id);
+
+            // the request waits, the page doesn't
+            AsyncManager::spawn_local_on_main_thread(async move {
+                policy::resolve(&host).await;
+
+                let navigation = policy::check_request(&url);
+                answer_request(&callback, &url, id, navigation);
+            });
+        }
+
+        navigation => answer_request(&callback, &url, id, navigation),
+    }
+}
+
+fn answer_request(callback: &RustRefRequestCallback, url: &str, id: c_int, navigation: Navigation) {
+    let allow = match navigation {
+        Navigation::Allow => true,
+
+        Navigation::Block(reason) => {
+            warn!("blocked request {} in browser {}: {}", url, id, reason);
+            false
+        }
+
+        // still unknown right after resolving
+        Navigation::Resolve(host) => {
+            warn!(
+                "blocked request {} in browser {}: couldn't resolve {}",
+                url, id, host
+            );
+            false
+        }
+    };
+
+    if let Err(e) = callback.answer(allow) {
+        warn!("{}", e);
+    }
+}
+
 thread_local!(
     pub static BROWSER_SIZES: RefCell<HashMap<c_int, (c_int, c_int)>> = Default::default();
 );
 
+// identifier, device scale factor
+thread_local!(
+    pub static BROWSER_SCALES: RefCell<HashMap<c_int, f32>> = Default::default();
+);
+
+pub extern "C" fn get_scale_factor(browser: RustRefBrowser) -> f32 {
+    let browser_id = browser.get_identifier();
+
+    BROWSER_SCALES.with(move |cell| cell.borrow().get(&browser_id).cloned().unwrap_or(1.0))
+}
+
 pub extern "C" fn get_view_rect(browser: RustRefBrowser) -> RustRect {
     let browser_id = browser.get_identifier();
 
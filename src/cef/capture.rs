@@ -0,0 +1,165 @@
+use super::{RustRect, RustRefBrowser};
+use crate::{
+    entity_manager::{cef_paint_callback, EntityManager},
+    error::*,
+};
+use async_std::future::timeout;
+use classicube_sys::Bitmap;
+use futures::channel::oneshot;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    os::raw::{c_int, c_void},
+    path::Path,
+    slice,
+    time::Duration,
+};
+
+// only browsers that aren't a screen wait, and hidden ones never paint
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// browser_id, senders waiting for the next paint
+thread_local!(
+    static WAITING_FOR_PAINT: RefCell<HashMap<c_int, Vec<oneshot::Sender<Capture>>>> =
+        Default::default();
+);
+
+/// A frame copied out of a browser's paint, in RGBA
+pub struct Capture {
+    pub pixels: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Capture {
+    pub fn from_bgra(bgra: &[u8], width: usize, height: usize) -> Self {
+        Self {
+            pixels: bgra_to_rgba(bgra),
+            width,
+            height,
+        }
+    }
+
+    /// Borrows our pixels for the game's drawing functions
+    pub fn as_bitmap(&mut self) -> Bitmap {
+        Bitmap {
+            Scan0: self.pixels.as_mut_ptr(),
+            Width: self.width as i32,
+            Height: self.height as i32,
+        }
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path)?;
+
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+
+        Ok(())
+    }
+}
+
+// OnPaint, hands the frame to anyone capturing before the entity gets it
+pub extern "C" fn on_paint(
+    browser: RustRefBrowser,
+    dirty_rects: *const RustRect,
+    dirty_rects_count: c_int,
+    pixels: *const c_void,
+    width: c_int,
+    height: c_int,
+) {
+    let browser_id = browser.get_identifier();
+
+    let senders = WAITING_FOR_PAINT.with(|cell| {
+        let waiting = &mut *cell.borrow_mut();
+        waiting.remove(&browser_id)
+    });
+
+    if let Some(senders) = senders {
+        let (width, height) = (width as usize, height as usize);
+        let bgra = unsafe { slice::from_raw_parts(pixels as *const u8, 4 * width * height) };
+        let capture = Capture::from_bgra(bgra, width, height);
+
+        for sender in senders {
+            let _ignore_error = sender.send(Capture {
+                pixels: capture.pixels.clone(),
+                width,
+                height,
+            });
+        }
+    }
+
+    cef_paint_callback(
+        browser,
+        dirty_rects,
+        dirty_rects_count,
+        pixels,
+        width,
+        height,
+    );
+}
+
+/// Resolves with the browser's latest full frame
+///
+/// Screens keep the last frame their browser painted, so that's used right
+/// away. Other browsers, or screens that haven't painted yet, wait for the
+/// next paint.
+pub async fn capture(browser: &RustRefBrowser) -> Result<Capture> {
+    let browser_id = browser.get_identifier();
+
+    let latest = EntityManager::with_by_browser_id(browser_id, |entity| Ok(entity.capture_frame()));
+    if let Ok(Some(capture)) = latest {
+        return Ok(capture);
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    WAITING_FOR_PAINT.with(|cell| {
+        let waiting = &mut *cell.borrow_mut();
+        waiting.entry(browser_id).or_default().push(sender);
+    });
+
+    // static pages don't paint on their own
+    browser.invalidate()?;
+
+    let result = timeout(CAPTURE_TIMEOUT, receiver).await;
+
+    // clean up if we timed out
+    WAITING_FOR_PAINT.with(|cell| {
+        let waiting = &mut *cell.borrow_mut();
+        if let Some(senders) = waiting.get_mut(&browser_id) {
+            senders.retain(|sender| !sender.is_canceled());
+            if senders.is_empty() {
+                waiting.remove(&browser_id);
+            }
+        }
+    });
+
+    let capture = result
+        .chain_err(|| "browser didn't paint, is it hidden?")?
+        .chain_err(|| "capture cancelled")?;
+
+    Ok(capture)
+}
+
+fn bgra_to_rgba(bgra: &[u8]) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(bgra.len());
+    for pixel in bgra.chunks_exact(4) {
+        rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+    }
+    rgba
+}
+
+#[test]
+fn test_bgra_to_rgba() {
+    assert_eq!(
+        bgra_to_rgba(&[1, 2, 3, 4, 5, 6, 7, 8]),
+        vec![3, 2, 1, 4, 7, 6, 5, 8]
+    );
+}
@@ -0,0 +1,135 @@
+use super::RustRefBrowser;
+use crate::error::*;
+use async_std::future::timeout;
+use futures::channel::oneshot;
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{cell::RefCell, collections::HashMap, ffi::CStr, os::raw::c_char, time::Duration};
+
+const EVAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// results are sent back to us through console.log with this prefix
+const EVAL_PREFIX: &str = "?CEF_EVAL?";
+
+// nonce, result sender
+//
+// Each eval gets a random nonce so a page can't answer for evals it
+// didn't see.
+thread_local!(
+    static WAITING_FOR_RESULT: RefCell<HashMap<String, oneshot::Sender<String>>> =
+        Default::default();
+);
+
+/// Stops waiting for a result when the eval finishes or is dropped
+struct Waiting {
+    nonce: String,
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        WAITING_FOR_RESULT.with(|cell| {
+            let waiting = &mut *cell.borrow_mut();
+            waiting.remove(&self.nonce);
+        });
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EvalResponse {
+    ok: bool,
+    value: serde_json::Value,
+}
+
+// OnConsoleMessage
+pub extern "C" fn on_console_message(_browser: RustRefBrowser, message: *const c_char) -> bool {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+
+    if !message.starts_with(EVAL_PREFIX) {
+        return false;
+    }
+
+    let message = &message[EVAL_PREFIX.len()..];
+    let mut parts = message.splitn(2, ':');
+    let (nonce, json) = match (parts.next(), parts.next()) {
+        (Some(nonce), Some(json)) => (nonce, json),
+        _ => {
+            warn!("bad eval response {:?}", message);
+            return true;
+        }
+    };
+
+    let maybe_sender = WAITING_FOR_RESULT.with(|cell| {
+        let waiting = &mut *cell.borrow_mut();
+        waiting.remove(nonce)
+    });
+
+    if let Some(sender) = maybe_sender {
+        let _ignore_error = sender.send(json.to_string());
+    } else {
+        debug!("no eval waiting for {:?}", nonce);
+    }
+
+    // don't print our results to the log
+    true
+}
+
+/// Wraps `code` so that its result (or a resolved Promise's result)
+/// gets JSON encoded and sent back through console.log
+fn wrap_code(nonce: &str, code: &str) -> Result<String> {
+    let prefix = serde_json::to_string(&format!("{}{}:", EVAL_PREFIX, nonce))?;
+    let code = serde_json::to_string(code)?;
+
+    Ok(format!(
+        r#"(function () {{
+            var send = function (ok, value) {{
+                console.log({} + JSON.stringify({{ ok: ok, value: value === undefined ? null : value }}));
+            }};
+            try {{
+                Promise.resolve(eval({})).then(
+                    function (value) {{ send(true, value); }},
+                    function (e) {{ send(false, String(e)); }}
+                );
+            }} catch (e) {{
+                send(false, String(e));
+            }}
+        }})();"#,
+        prefix, code
+    ))
+}
+
+pub async fn eval<T: DeserializeOwned>(browser: &RustRefBrowser, code: &str) -> Result<T> {
+    let nonce = format!(
+        "{:016x}{:016x}",
+        rand::random::<u64>(),
+        rand::random::<u64>()
+    );
+
+    let (sender, receiver) = oneshot::channel();
+    WAITING_FOR_RESULT.with(|cell| {
+        let waiting = &mut *cell.borrow_mut();
+        waiting.insert(nonce.clone(), sender);
+    });
+
+    // cleans up if we time out, fail to send, or get dropped
+    let waiting = Waiting { nonce };
+
+    browser.execute_javascript(wrap_code(&waiting.nonce, code)?)?;
+
+    let json = timeout(EVAL_TIMEOUT, receiver)
+        .await
+        .chain_err(|| "javascript eval timed out")?
+        .chain_err(|| "javascript eval cancelled")?;
+
+    let response: EvalResponse = serde_json::from_str(&json)?;
+    if response.ok {
+        Ok(serde_json::from_value(response.value)?)
+    } else {
+        let message = response
+            .value
+            .as_str()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| response.value.to_string());
+
+        Err(ErrorKind::JavascriptError(message).into())
+    }
+}
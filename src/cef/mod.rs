@@ -1,12 +1,18 @@
 mod bindings;
 mod browser;
+mod capture;
+mod javascript;
 
-pub use self::bindings::{Callbacks, RustRefApp, RustRefBrowser, RustRefClient};
-use self::browser::{BROWSERS, BROWSER_SIZES};
+use self::browser::{BROWSERS, BROWSER_SCALES, BROWSER_SIZES, MEDIA_BROWSERS};
+pub use self::{
+    bindings::{Callbacks, RustRect, RustRefApp, RustRefBrowser, RustRefClient},
+    capture::Capture,
+};
 use crate::{
     async_manager::AsyncManager,
-    entity_manager::{cef_paint_callback, TEXTURE_HEIGHT, TEXTURE_WIDTH},
+    entity_manager::{TEXTURE_HEIGHT, TEXTURE_WIDTH},
     error::*,
+    metrics,
 };
 use classicube_helpers::{shared::FutureShared, CellGetSet, OptionWithInner};
 use futures::stream::{FuturesUnordered, StreamExt};
@@ -15,7 +21,7 @@ use std::{
     cell::{Cell, RefCell},
     collections::HashMap,
     os::raw::c_int,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::sync::broadcast;
 
@@ -76,8 +82,12 @@ impl Cef {
             on_before_close_callback: Some(browser::on_before_close),
             on_load_end_callback: Some(browser::on_page_loaded),
             on_title_change_callback: Some(browser::on_title_change),
-            on_paint_callback: Some(cef_paint_callback),
+            on_paint_callback: Some(capture::on_paint),
             get_view_rect_callback: Some(browser::get_view_rect),
+            on_console_message_callback: Some(javascript::on_console_message),
+            on_before_browse_callback: Some(browser::on_before_browse),
+            get_scale_factor_callback: Some(browser::get_scale_factor),
+            on_before_resource_load_callback: Some(browser::on_before_resource_load),
         });
 
         let mut event_receiver = Self::create_event_listener();
@@ -93,7 +103,12 @@ impl Cef {
         IS_INITIALIZED.set(true);
 
         AsyncManager::spawn_local_on_main_thread(async move {
-            while crate::time_silent!("Cef::try_step()", 100, { Cef::try_step() }) {
+            while crate::time_silent!("Cef::try_step()", 100, {
+                let before = Instant::now();
+                let stepped = Cef::try_step();
+                metrics::record_step(before.elapsed());
+                stepped
+            }) {
                 AsyncManager::sleep(CEF_RATE).await;
             }
         });
@@ -258,6 +273,33 @@ impl Cef {
         Ok(())
     }
 
+    /// Paints `scale` pixels per view pixel, the page's layout stays the same
+    pub fn set_browser_scale(browser: &RustRefBrowser, scale: f32) -> Result<()> {
+        let browser_id = browser.get_identifier();
+        BROWSER_SCALES.with(move |cell| {
+            let scales = &mut *cell.borrow_mut();
+
+            scales.insert(browser_id, scale);
+        });
+
+        browser.notify_screen_info_changed()?;
+        Ok(())
+    }
+
+    /// Media pages may load what the policy only allows for media
+    pub fn set_browser_is_media(browser: &RustRefBrowser, is_media: bool) {
+        let browser_id = browser.get_identifier();
+        MEDIA_BROWSERS.with(move |cell| {
+            let media_browsers = &mut *cell.borrow_mut();
+
+            if is_media {
+                media_browsers.insert(browser_id);
+            } else {
+                media_browsers.remove(&browser_id);
+            }
+        });
+    }
+
     pub fn get_browser_size(browser: &RustRefBrowser) -> (c_int, c_int) {
         let browser_id = browser.get_identifier();
         BROWSER_SIZES.with(move |cell| {
@@ -1,41 +1,41 @@
-use super::Chat;
+use super::{
+    commands::{self, parse_number, parse_on_off, Command, Handler, Permission, Target},
+    completion::{self, TabCompletion},
+    permissions::{self, Access, Action},
+    Chat,
+};
 use crate::{
     async_manager::AsyncManager,
     cef::Cef,
-    chat::{PlayerSnapshot, ENTITIES},
-    entity_manager::{CefEntity, EntityManager, MODEL_HEIGHT, MODEL_WIDTH},
+    chat::PlayerSnapshot,
+    entity_manager::{
+        self,
+        limits::{self, Hide},
+        AudioScheduler, CefEntity, EditMode, EntityManager, FaceMode, Label, StatsHud, Transform,
+    },
     error::*,
-    players::PlayerTrait,
+    host, metrics,
+    players::{policy, PlayerTrait},
     search,
 };
 use classicube_sys::{OwnedChatCommand, Vec3, ENTITIES_SELF_ID};
 use log::{debug, warn};
-use std::{os::raw::c_int, slice, time::Duration};
+use nalgebra::Vector3;
+use std::{
+    convert::TryInto,
+    os::raw::c_int,
+    slice,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 extern "C" fn c_chat_command_callback(args: *const classicube_sys::String, args_count: c_int) {
     let args = unsafe { slice::from_raw_parts(args, args_count as _) };
     let args: Vec<String> = args.iter().map(|cc_string| cc_string.to_string()).collect();
 
-    let player_snapshot = ENTITIES
-        .with(|cell| {
-            let entities = &*cell.borrow();
-            let entities = entities.as_ref().unwrap();
-            entities.get(ENTITIES_SELF_ID as _).map(|entity| {
-                let position = entity.get_position();
-                let eye_position = entity.get_eye_position();
-                let head = entity.get_head();
-                let rot = entity.get_rot();
-                PlayerSnapshot {
-                    Position: position,
-                    eye_position,
-                    Pitch: head[0],
-                    Yaw: head[1],
-                    RotX: rot[0],
-                    RotY: rot[1],
-                    RotZ: rot[2],
-                }
-            })
-        })
+    let host = host::get();
+    let player_snapshot = host
+        .get_player(ENTITIES_SELF_ID as _)
+        .map(|player| PlayerSnapshot::new(host.get_username(), false, &player))
         .unwrap();
 
     AsyncManager::spawn_local_on_main_thread(async move {
@@ -59,117 +59,600 @@ fn move_entity(entity: &mut CefEntity, player: &PlayerSnapshot) {
     entity.entity.RotX = 360f32 - player.Pitch;
 }
 
+/// owner of the screen a command would affect
+fn get_target_owner(player: &PlayerSnapshot, command: &Command, args: &[&str]) -> Option<String> {
+    let get_owner = |entity: &mut CefEntity| Ok(entity.get_trusted_owner().map(|s| s.to_string()));
+
+    match command.target {
+        // new screens and all screens don't have a single owner
+        Target::Nothing | Target::All => None,
+
+        Target::ById => {
+            let entity_id: usize = args.get(0)?.parse().ok()?;
+            EntityManager::with_by_entity_id(entity_id, get_owner).ok()?
+        }
+
+        Target::Closest => EntityManager::with_closest(player.eye_position, get_owner).ok()?,
+    }
+}
+
+/// `args` as exactly `N` words, `commands::parse` already checked how many
+///
+/// `N` can't be inferred from the pattern it's destructured with.
+fn exactly<'a, const N: usize>(command: &Command, args: &'a [&'a str]) -> Result<&'a [&'a str; N]> {
+    args.try_into()
+        .ok()
+        .chain_err(|| format!("usage: {}", command.usage()))
+}
+
+/// if the closest screen is waiting for us to accept its url
+fn is_pending(player: &PlayerSnapshot) -> bool {
+    EntityManager::with_closest(player.eye_position, |entity| {
+        Ok(entity.get_pending_requester().is_some())
+    })
+    .unwrap_or(false)
+}
+
+fn get_owner_name(player: &PlayerSnapshot) -> Option<String> {
+    if player.name.is_empty() {
+        None
+    } else {
+        Some(player.name.clone())
+    }
+}
+
 pub async fn command_callback(
     player: &PlayerSnapshot,
     args: Vec<String>,
     is_self: bool,
 ) -> Result<()> {
     debug!("command_callback {:?}", args);
-    let args: Vec<&str> = args.iter().map(|s| s.as_ref()).collect();
-    let args: &[&str] = &args;
 
-    // static commands not targetted at a specific entity
-    match args {
-        ["create"] => {
-            let entity_id = EntityManager::create_entity("https://www.classicube.net/")?;
-            EntityManager::with_by_entity_id(entity_id, |entity| {
-                move_entity(entity, player);
+    // other players' urls might need accepting first
+    let requester = if is_self {
+        None
+    } else {
+        Some(player.name.clone())
+    };
+
+    let words: Vec<&str> = args.iter().map(|s| s.as_ref()).collect();
+    let (command, args) = commands::parse(&words)?;
+
+    if !is_self {
+        match command.permission {
+            Permission::Me => {
+                debug!(
+                    "ignoring {:?} from {:?}: only we can run it",
+                    words, player.name
+                );
+                return Ok(());
+            }
 
-                Ok(())
+            Permission::Action(action) => {
+                let owner = get_target_owner(player, command, args);
+
+                if !permissions::is_allowed(&player.name, player.is_op, action, owner.as_deref()) {
+                    warn!(
+                        "ignoring {:?} from {:?}: not allowed to {:?}",
+                        words, player.name, action
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // no wildcard, so every command needs a handler to compile
+    match command.handler {
+        Handler::Help => commands::print_help(args)?,
+
+        Handler::Create => match args {
+            [] => {
+                let entity_id = EntityManager::create_entity(
+                    "https://www.classicube.net/",
+                    get_owner_name(player),
+                    requester,
+                )?;
+                EntityManager::with_by_entity_id(entity_id, |entity| {
+                    move_entity(entity, player);
+
+                    Ok(())
+                })?;
+            }
+            url => {
+                let url: String = url.concat();
+                if is_self {
+                    completion::remember_url(&url);
+                }
+
+                let entity_id =
+                    EntityManager::create_entity(&url, get_owner_name(player), requester)?;
+                EntityManager::with_by_entity_id(entity_id, |entity| {
+                    move_entity(entity, player);
+
+                    Ok(())
+                })?;
+            }
+        },
+
+        Handler::Search => {
+            let input = args.join(" ");
+            let id = search::youtube::search(&input).await?;
+
+            Chat::send(format!("cef play {}", id));
+        }
+
+        Handler::Friend => {
+            let [name] = exactly::<1>(command, args)?;
+
+            permissions::add_friend(name);
+            Chat::print(format!("{} added to friends", name));
+        }
+
+        Handler::Unfriend => {
+            let [name] = exactly::<1>(command, args)?;
+
+            permissions::remove_friend(name);
+            Chat::print(format!("{} removed from friends", name));
+        }
+
+        Handler::Block => {
+            let [name] = exactly::<1>(command, args)?;
+
+            permissions::block(name);
+            Chat::print(format!("{} ignored", name));
+        }
+
+        Handler::Unblock => {
+            let [name] = exactly::<1>(command, args)?;
+
+            permissions::unblock(name);
+            Chat::print(format!("{} unignored", name));
+        }
+
+        Handler::Allow => {
+            let [action, access] = exactly::<2>(command, args)?;
+
+            let action = Action::parse(action)?;
+            let access = Access::parse(access)?;
+            permissions::set_access(action, access);
+
+            Chat::print(format!("{:?} allowed for {:?}", action, access));
+        }
+
+        Handler::Permissions => {
+            for line in permissions::describe() {
+                Chat::print(line);
+            }
+        }
+
+        Handler::Policy => {
+            let policy = policy::get();
+            Chat::print(format!(
+                "media only: {}, block private: {}",
+                policy.media_only, policy.block_private
+            ));
+            Chat::print(format!("allow: {}", policy.allow.join(", ")));
+            Chat::print(format!("deny: {}", policy.deny.join(", ")));
+        }
+
+        Handler::PolicyAllow => {
+            let [pattern] = exactly::<1>(command, args)?;
+
+            let pattern = pattern.to_lowercase();
+            policy::update(|policy| {
+                policy.deny.retain(|p| *p != pattern);
+                if !policy.allow.contains(&pattern) {
+                    policy.allow.push(pattern);
+                }
             })?;
         }
 
-        ["create", ..] => {
-            let url: String = args.iter().skip(1).copied().collect();
+        Handler::PolicyDeny => {
+            let [pattern] = exactly::<1>(command, args)?;
 
-            let entity_id = EntityManager::create_entity(&url)?;
-            EntityManager::with_by_entity_id(entity_id, |entity| {
-                move_entity(entity, player);
+            let pattern = pattern.to_lowercase();
+            policy::update(|policy| {
+                policy.allow.retain(|p| *p != pattern);
+                if !policy.deny.contains(&pattern) {
+                    policy.deny.push(pattern);
+                }
+            })?;
+        }
 
-                Ok(())
+        Handler::PolicyRemove => {
+            let [pattern] = exactly::<1>(command, args)?;
+
+            let pattern = pattern.to_lowercase();
+            policy::update(|policy| {
+                policy.allow.retain(|p| *p != pattern);
+                policy.deny.retain(|p| *p != pattern);
             })?;
         }
 
-        ["search", ..] => {
-            if is_self {
-                let input: Vec<_> = args.iter().skip(1).copied().collect();
-                let input = input.join(" ");
-                let input = (*input).to_string();
-                let id = search::youtube::search(&input).await?;
+        Handler::PolicyMedia => {
+            let [on_off] = exactly::<1>(command, args)?;
+
+            let media_only = parse_on_off(on_off)?;
+            policy::update(|policy| policy.media_only = media_only)?;
+        }
+
+        Handler::PolicyPrivate => {
+            let [on_off] = exactly::<1>(command, args)?;
+
+            let block_private = parse_on_off(on_off)?;
+            policy::update(|policy| policy.block_private = block_private)?;
+        }
+
+        Handler::Ask => {
+            let [on_off] = exactly::<1>(command, args)?;
+
+            let ask_first = parse_on_off(on_off)?;
+            policy::update(|policy| policy.ask_first = ask_first)?;
+        }
+
+        Handler::Limits => {
+            let limits = limits::get();
+            Chat::print(format!(
+                "screens: {}, per owner: {}, memory: {} MB, hide: {:?}",
+                limits.max_screens,
+                limits.max_screens_per_owner,
+                limits.texture_budget / 1024 / 1024,
+                limits.hide
+            ));
+        }
+
+        Handler::Screenshot => {
+            let entity_id =
+                EntityManager::with_closest(player.eye_position, |entity| Ok(entity.id))?;
+            let browser = EntityManager::get_browser_by_entity_id(entity_id)?;
+
+            // cef.log is written to the working directory
+            let path = if args.is_empty() {
+                let seconds = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or(0);
+                format!("cef-screenshot-{}-{}.png", entity_id, seconds)
+            } else {
+                args.join(" ")
+            };
+
+            let capture = browser.capture().await?;
+            capture.save_png(&path)?;
+
+            Chat::print(format!("saved screenshot to {}", path));
+        }
 
-                Chat::send(format!("cef play {}", id));
+        Handler::Frames => {
+            let stats = EntityManager::with_closest(player.eye_position, |entity| {
+                Ok(entity.get_frame_stats())
+            })?;
+            Chat::print(format!(
+                "paints/sec: {:.1}, uploads/sec: {:.1}, dropped: {}",
+                stats.paints_per_second, stats.uploads_per_second, stats.dropped
+            ));
+        }
+
+        Handler::Stats => {
+            for line in metrics::get_summary() {
+                Chat::print(line);
             }
         }
 
-        _ => {}
-    }
+        Handler::StatsHud => match args {
+            [] => {
+                StatsHud::set_enabled(!StatsHud::is_enabled());
+            }
+            [on_off] => {
+                StatsHud::set_enabled(parse_on_off(on_off)?);
+            }
+            _ => bail!("usage: {}", command.usage()),
+        },
+
+        Handler::LimitScreens => {
+            let [count] = exactly::<1>(command, args)?;
+
+            let count = count.parse()?;
+            limits::update(|limits| limits.max_screens = count)?;
+            EntityManager::apply_limits(true);
+        }
+
+        Handler::LimitOwner => {
+            let [count] = exactly::<1>(command, args)?;
+
+            let count = count.parse()?;
+            limits::update(|limits| limits.max_screens_per_owner = count)?;
+            EntityManager::apply_limits(true);
+        }
+
+        Handler::LimitMemory => {
+            let [megabytes] = exactly::<1>(command, args)?;
+
+            let megabytes: usize = megabytes.parse()?;
+            limits::update(|limits| limits.texture_budget = megabytes * 1024 * 1024)?;
+            EntityManager::apply_limits(true);
+        }
+
+        Handler::LimitHide => {
+            let [hide] = exactly::<1>(command, args)?;
+
+            let hide = Hide::parse(hide)?;
+            limits::update(|limits| limits.hide = hide)?;
+            EntityManager::apply_limits(true);
+        }
+
+        Handler::Labels => match args {
+            [] => {
+                Label::set_enabled(!Label::is_enabled());
+            }
+            [on_off] => {
+                Label::set_enabled(parse_on_off(on_off)?);
+            }
+            _ => bail!("usage: {}", command.usage()),
+        },
+
+        Handler::Volume => {
+            let [volume] = exactly::<1>(command, args)?;
+
+            let percent = parse_number(volume)?;
+            if !(0.0..=100.0).contains(&percent) {
+                bail!("usage: {}", command.usage());
+            }
+            AudioScheduler::set_global_volume(percent / 100.0);
+
+            Chat::print(format!(
+                "cef volume set to {}%",
+                (AudioScheduler::get_global_volume() * 100.0) as u32
+            ));
+        }
+
+        Handler::HereById => {
+            let [entity_id] = exactly::<1>(command, args)?;
 
-    // commands that target a certain entity by id
-    #[allow(clippy::single_match)]
-    match args {
-        ["here", entity_id] => {
             let entity_id: usize = entity_id.parse()?;
 
             EntityManager::with_by_entity_id(entity_id, |entity| {
+                entity.save_transform();
                 move_entity(entity, player);
 
                 Ok(())
             })?;
         }
 
-        _ => {}
-    }
-
-    // commands that target the closest entity/browser
-    match args {
-        ["here"] | ["move"] => EntityManager::with_closest(player.eye_position, |entity| {
+        Handler::Here => EntityManager::with_closest(player.eye_position, |entity| {
+            entity.save_transform();
             move_entity(entity, player);
 
             Ok(())
         })?,
 
-        ["at", x, y, z] | ["tp", x, y, z] => {
+        Handler::PlaceWall => EntityManager::with_closest(player.eye_position, |entity| {
+            entity.change_transform(|entity| {
+                entity_manager::place_on_wall(entity, player.eye_position, player.Yaw, player.Pitch)
+            })
+        })?,
+
+        Handler::Edit => {
+            let entity_id =
+                EntityManager::with_closest(player.eye_position, |entity| Ok(entity.id))?;
+
+            EditMode::start(entity_id, player.eye_position)?;
+        }
+
+        Handler::Snap => EntityManager::with_closest(player.eye_position, |entity| {
+            entity.save_transform();
+            entity_manager::snap(entity);
+
+            Ok(())
+        })?,
+
+        Handler::At => {
+            let [x, y, z] = exactly::<3>(command, args)?;
+
             EntityManager::with_closest(player.eye_position, |entity| {
-                let x = x.parse()?;
-                let y = y.parse()?;
-                let z = z.parse()?;
+                let x = parse_number(x)?;
+                let y = parse_number(y)?;
+                let z = parse_number(z)?;
 
+                entity.save_transform();
                 entity.entity.Position.set(x, y, z);
 
+                Ok(())
+            })?;
+        }
+
+        Handler::Angles => match args {
+            [pitch, yaw, roll @ ..] => {
+                EntityManager::with_closest(player.eye_position, |entity| {
+                    let pitch = parse_number(pitch)?;
+                    let yaw = parse_number(yaw)?;
+                    let roll = match roll {
+                        [roll] => parse_number(roll)?,
+                        _ => entity.get_angles()[2],
+                    };
+
+                    entity.save_transform();
+                    entity.set_angles([pitch, yaw, roll]);
+
+                    Ok(())
+                })?
+            }
+            _ => bail!("usage: {}", command.usage()),
+        },
+
+        Handler::Scale => {
+            let [scale] = exactly::<1>(command, args)?;
+
+            EntityManager::with_closest(player.eye_position, |entity| {
+                let scale = parse_number(scale)?;
+
+                entity.change_transform(|entity| entity.set_scale(scale))
+            })?;
+        }
+
+        Handler::Size => {
+            let [width, height] = exactly::<2>(command, args)?;
+
+            EntityManager::with_closest(player.eye_position, |entity| {
+                let width = parse_number(width)?;
+                let height = parse_number(height)?;
+
+                entity.change_transform(|entity| entity.set_size([width, height]))
+            })?;
+        }
+
+        Handler::Transform => {
+            let [entity_id, x, y, z, pitch, yaw, roll, width, height] =
+                exactly::<9>(command, args)?;
+
+            let entity_id: usize = entity_id.parse()?;
+            let transform = Transform {
+                position: [parse_number(x)?, parse_number(y)?, parse_number(z)?],
+                angles: [
+                    parse_number(pitch)?,
+                    parse_number(yaw)?,
+                    parse_number(roll)?,
+                ],
+                size: [parse_number(width)?, parse_number(height)?],
+            };
+
+            EntityManager::with_by_entity_id(entity_id, |entity| {
+                entity.change_transform(|entity| transform.apply(entity))
+            })?;
+        }
+
+        Handler::Nudge => {
+            let [direction, amount] = exactly::<2>(command, args)?;
+
+            EntityManager::with_closest(player.eye_position, |entity| {
+                let direction = parse_direction(direction)?;
+                let amount = parse_number(amount)?;
+
+                entity.save_transform();
+                entity_manager::nudge(entity, direction, amount);
+
                 Ok(())
             })?
         }
 
-        ["angles", pitch, yaw] | ["angle", pitch, yaw] => {
+        Handler::Rotate => {
+            let [axis, degrees] = exactly::<2>(command, args)?;
+
             EntityManager::with_closest(player.eye_position, |entity| {
-                let pitch = pitch.parse()?;
-                let yaw = yaw.parse()?;
+                let degrees = parse_number(degrees)?;
+                let angles = match *axis {
+                    "pitch" => [degrees, 0.0, 0.0],
+                    "yaw" => [0.0, degrees, 0.0],
+                    "roll" => [0.0, 0.0, degrees],
+                    _ => bail!("expected pitch, yaw or roll"),
+                };
 
-                entity.entity.RotX = pitch;
-                entity.entity.RotY = yaw;
+                entity.save_transform();
+                entity_manager::rotate(entity, angles);
 
                 Ok(())
             })?
         }
 
-        ["scale", scale] => EntityManager::with_closest(player.eye_position, |entity| {
-            let scale = scale.parse()?;
+        Handler::Grow => {
+            let [factor] = exactly::<1>(command, args)?;
 
-            entity.set_scale(scale);
+            EntityManager::with_closest(player.eye_position, |entity| {
+                let factor = parse_number(factor)?;
+                if factor <= 0.0 {
+                    bail!("factor must be positive");
+                }
 
-            Ok(())
-        })?,
+                entity.change_transform(|entity| entity_manager::grow(entity, factor))
+            })?;
+        }
+
+        Handler::Undo => {
+            EntityManager::with_closest(player.eye_position, |entity| entity.undo_transform())?
+        }
+
+        Handler::Redo => {
+            EntityManager::with_closest(player.eye_position, |entity| entity.redo_transform())?
+        }
+
+        Handler::Name => match args {
+            [] => EntityManager::with_closest(player.eye_position, |entity| {
+                entity.set_name(None);
+
+                Ok(())
+            })?,
+            name => EntityManager::with_closest(player.eye_position, |entity| {
+                entity.set_name(Some(name.join(" ")));
+
+                Ok(())
+            })?,
+        },
+
+        Handler::Opacity => {
+            let [opacity] = exactly::<1>(command, args)?;
+
+            EntityManager::with_closest(player.eye_position, |entity| {
+                let opacity = parse_number(opacity)?;
+
+                entity.set_opacity(opacity);
+
+                Ok(())
+            })?;
+        }
+
+        Handler::Tint => {
+            let [color] = exactly::<1>(command, args)?;
+
+            EntityManager::with_closest(player.eye_position, |entity| {
+                entity.set_tint(parse_color(color)?);
+
+                Ok(())
+            })?;
+        }
+
+        Handler::Lit => {
+            let [on_off] = exactly::<1>(command, args)?;
+
+            EntityManager::with_closest(player.eye_position, |entity| {
+                entity.set_lit(parse_on_off(on_off)?);
+
+                Ok(())
+            })?;
+        }
+
+        Handler::Sides => match args {
+            [mode, color @ ..] => EntityManager::with_closest(player.eye_position, |entity| {
+                let face_mode = match (*mode, color) {
+                    ("single", []) => FaceMode::SingleSided,
+                    ("mirror", []) | ("mirrored", []) | ("double", []) => {
+                        FaceMode::DoubleSidedMirrored
+                    }
+                    ("flat", []) => FaceMode::DoubleSidedFlat([0, 0, 0]),
+                    ("flat", [color]) => FaceMode::DoubleSidedFlat(parse_color(color)?),
+                    _ => bail!("expected single, mirror or flat [color]"),
+                };
 
-        ["load", ..] | ["play", ..] => {
-            let url: String = args.iter().skip(1).copied().collect();
+                entity.set_face_mode(face_mode);
+
+                Ok(())
+            })?,
+            _ => bail!("usage: {}", command.usage()),
+        },
+
+        Handler::Load => {
+            let url: String = args.concat();
+            if is_self {
+                completion::remember_url(&url);
+            }
 
             let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
                 Ok(closest_entity.id)
             })?;
-            EntityManager::entity_play(&url, entity_id)?;
+            EntityManager::entity_play(&url, entity_id, requester)?;
         }
 
-        ["stop"] => {
+        Handler::Stop => {
             let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
                 Ok(closest_entity.id)
             })?;
@@ -178,7 +661,7 @@ pub async fn command_callback(
             browser.load_url("data:text/html,")?;
         }
 
-        ["close"] | ["remove"] | ["clear"] => {
+        Handler::Close => {
             let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
                 Ok(closest_entity.id)
             })?;
@@ -190,13 +673,13 @@ pub async fn command_callback(
             });
         }
 
-        ["closeall"] | ["removeall"] | ["stopall"] | ["clearall"] => {
+        Handler::CloseAll => {
             AsyncManager::spawn_local_on_main_thread(async {
                 let _ignore_error = EntityManager::remove_all_entities().await;
             });
         }
 
-        ["refresh"] | ["reload"] => {
+        Handler::Refresh => {
             let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
                 Ok(closest_entity.id)
             })?;
@@ -204,109 +687,115 @@ pub async fn command_callback(
             browser.reload()?;
         }
 
-        ["click"] => {
-            let (entity_id, entity_pos, [entity_pitch, entity_yaw], entity_scale) =
-                EntityManager::with_closest(player.eye_position, |closest_entity| {
-                    Ok((
-                        closest_entity.id,
-                        closest_entity.entity.Position,
-                        [closest_entity.entity.RotX, closest_entity.entity.RotY],
-                        closest_entity.entity.ModelScale,
-                    ))
-                })?;
+        Handler::Accept => {
+            let entity_id =
+                EntityManager::with_closest(player.eye_position, |entity| Ok(entity.id))?;
 
-            use nalgebra::*;
-            use ncollide3d::{query::*, shape::*};
-
-            fn intersect(
-                eye_pos: Point3<f32>,
-                [aim_pitch, aim_yaw]: [f32; 2],
-                screen_pos: Point3<f32>,
-                [screen_pitch, screen_yaw]: [f32; 2],
-            ) -> Option<(Ray<f32>, Plane<f32>, RayIntersection<f32>)> {
-                // when angles 0 0, aiming towards -z
-                let normal = -Vector3::<f32>::z_axis();
-
-                let aim_dir = Rotation3::from_euler_angles(
-                    -aim_pitch.to_radians(),
-                    -aim_yaw.to_radians(),
-                    0.0,
-                )
-                .transform_vector(&normal);
-
-                // positive pitch is clockwise on the -x axis
-                // positive yaw is clockwise on the -y axis
-                let rot = UnitQuaternion::from_euler_angles(
-                    -screen_pitch.to_radians(),
-                    -screen_yaw.to_radians(),
-                    0.0,
-                );
-                let iso = Isometry3::from_parts(screen_pos.coords.into(), rot);
+            EntityManager::accept_pending(entity_id, !args.is_empty())?;
+        }
 
-                let ray = Ray::new(eye_pos, aim_dir);
-                let plane = Plane::new(normal);
-                if let Some(intersection) = plane.toi_and_normal_with_ray(&iso, &ray, 10.0, true) {
-                    if intersection.toi == 0.0 {
-                        // 0 if aiming from wrong side
-                        None
+        Handler::Click => match args {
+            [] if is_self && is_pending(player) => {
+                let entity_id =
+                    EntityManager::with_closest(player.eye_position, |entity| Ok(entity.id))?;
+
+                EntityManager::accept_pending(entity_id, false)?;
+            }
+            [] => {
+                let (entity_id, entity_pos, entity_rot, [width, height], double_sided) =
+                    EntityManager::with_closest(player.eye_position, |closest_entity| {
+                        Ok((
+                            closest_entity.id,
+                            closest_entity.entity.Position,
+                            closest_entity.get_rotation(),
+                            closest_entity.get_size(),
+                            closest_entity.get_face_mode().is_double_sided(),
+                        ))
+                    })?;
+
+                use nalgebra::*;
+                use ncollide3d::{query::*, shape::*};
+
+                fn intersect(
+                    eye_pos: Point3<f32>,
+                    [aim_pitch, aim_yaw]: [f32; 2],
+                    screen_pos: Point3<f32>,
+                    screen_rot: UnitQuaternion<f32>,
+                    double_sided: bool,
+                ) -> Option<(Ray<f32>, f32)> {
+                    // when angles 0 0, aiming towards -z
+                    let normal = -Vector3::<f32>::z_axis();
+
+                    let aim_dir = Rotation3::from_euler_angles(
+                        -aim_pitch.to_radians(),
+                        -aim_yaw.to_radians(),
+                        0.0,
+                    )
+                    .transform_vector(&normal);
+
+                    let iso = Isometry3::from_parts(screen_pos.coords.into(), screen_rot);
+
+                    let ray = Ray::new(eye_pos, aim_dir);
+                    let plane = Plane::new(normal);
+
+                    // a solid plane only lets us hit it from the front
+                    let solid = !double_sided;
+                    if let Some(toi) = plane.toi_with_ray(&iso, &ray, 10.0, solid) {
+                        if toi == 0.0 {
+                            // 0 if aiming from wrong side
+                            None
+                        } else {
+                            Some((ray, toi))
+                        }
                     } else {
-                        Some((ray, plane, intersection))
+                        None
                     }
-                } else {
-                    None
                 }
-            }
-
-            fn vec3_to_vector3(v: &Vec3) -> Vector3<f32> {
-                Vector3::new(v.X, v.Y, v.Z)
-            }
-
-            let eye_pos = vec3_to_vector3(&player.eye_position);
-            let screen_pos = vec3_to_vector3(&entity_pos);
 
-            if let Some((ray, _plane, intersection)) = intersect(
-                eye_pos.into(),
-                [player.Pitch, player.Yaw],
-                screen_pos.into(),
-                [entity_pitch, entity_yaw],
-            ) {
-                let intersection_point = ray.point_at(intersection.toi).coords;
+                fn vec3_to_vector3(v: &Vec3) -> Vector3<f32> {
+                    Vector3::new(v.X, v.Y, v.Z)
+                }
 
-                let forward = intersection.normal;
+                let eye_pos = vec3_to_vector3(&player.eye_position);
+                let screen_pos = vec3_to_vector3(&entity_pos);
 
-                let tmp = Vector3::y();
-                let right = Vector3::cross(&forward, &tmp);
-                let right = right.normalize();
-                let up = Vector3::cross(&right, &forward);
-                let up = up.normalize();
-                let right = -right;
+                if let Some((ray, toi)) = intersect(
+                    eye_pos.into(),
+                    [player.Pitch, player.Yaw],
+                    screen_pos.into(),
+                    entity_rot,
+                    double_sided,
+                ) {
+                    let intersection_point = ray.point_at(toi).coords;
 
-                let width = entity_scale.X * MODEL_WIDTH as f32;
-                let height = entity_scale.Y * MODEL_HEIGHT as f32;
+                    // always use the front's axes so that clicking from behind
+                    // hits the same spot on the page
+                    let right = entity_rot.transform_vector(&-Vector3::x());
+                    let up = entity_rot.transform_vector(&Vector3::y());
 
-                let top_left = screen_pos - 0.5 * right * width + up * height;
+                    let top_left = screen_pos - 0.5 * right * width + up * height;
 
-                let diff = intersection_point - top_left;
-                let x = diff.dot(&right) / width;
-                let y = -(diff.dot(&up) / height);
+                    let diff = intersection_point - top_left;
+                    let x = diff.dot(&right) / width;
+                    let y = -(diff.dot(&up) / height);
 
-                if x < 0.0 || x > 1.0 || y < 0.0 || y > 1.0 {
-                    return Err("not looking at a screen".into());
-                }
+                    if x < 0.0 || x > 1.0 || y < 0.0 || y > 1.0 {
+                        return Err("not looking at a screen".into());
+                    }
 
-                let browser = EntityManager::get_browser_by_entity_id(entity_id)?;
-                let (browser_width, browser_height) = Cef::get_browser_size(&browser);
+                    let browser = EntityManager::get_browser_by_entity_id(entity_id)?;
+                    let (browser_width, browser_height) = Cef::get_browser_size(&browser);
 
-                let (x, y) = (x * browser_width as f32, y * browser_height as f32);
+                    let (x, y) = (x * browser_width as f32, y * browser_height as f32);
 
-                browser.send_click(x as _, y as _)?;
+                    browser.send_click(x as _, y as _)?;
+                }
             }
-        }
+            _ => bail!("usage: {}", command.usage()),
+        },
 
-        ["type", ..] => {
-            let text: Vec<_> = args.iter().skip(1).copied().collect();
-            let text = text.join(" ");
-            let text = (*text).to_string();
+        Handler::Type => {
+            let text = args.join(" ");
 
             let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
                 Ok(closest_entity.id)
@@ -316,7 +805,9 @@ pub async fn command_callback(
             browser.send_text(text)?;
         }
 
-        ["click", x, y] => {
+        Handler::ClickAt => {
+            let [x, y] = exactly::<2>(command, args)?;
+
             let x = x.parse()?;
             let y = y.parse()?;
 
@@ -328,7 +819,9 @@ pub async fn command_callback(
             browser.send_click(x, y)?;
         }
 
-        ["time", time] | ["seek", time] => {
+        Handler::Time => {
+            let [time] = exactly::<1>(command, args)?;
+
             let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
                 Ok(closest_entity.id)
             })?;
@@ -374,38 +867,64 @@ pub async fn command_callback(
             })?;
         }
 
-        ["resize", width, height] => {
+        Handler::Resize => {
+            let [width, height] = exactly::<2>(command, args)?;
+
             let width = width.parse()?;
             let height = height.parse()?;
 
-            let entity_id = EntityManager::with_closest(player.eye_position, |closest_entity| {
-                Ok(closest_entity.id)
+            EntityManager::with_closest(player.eye_position, |entity| {
+                entity.change_transform(|entity| entity.set_resolution(width, height))
             })?;
-
-            let browser = EntityManager::get_browser_by_entity_id(entity_id)?;
-            Cef::resize_browser(&browser, width, height)?;
         }
-
-        _ => {}
     }
 
     Ok(())
 }
 
+/// parses a hex color like ff8080
+fn parse_color(color: &str) -> Result<[u8; 3]> {
+    let color = color.trim_start_matches('#');
+    if color.len() != 6 {
+        bail!("color must be 6 hex digits like ff8080");
+    }
+    let color = u32::from_str_radix(color, 16)?;
+
+    Ok([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+}
+
+/// direction in the screen's own frame
+fn parse_direction(direction: &str) -> Result<Vector3<f32>> {
+    Ok(match direction {
+        "forward" | "forwards" | "front" => -Vector3::z(),
+        "back" | "backward" | "backwards" => Vector3::z(),
+        "right" => -Vector3::x(),
+        "left" => Vector3::x(),
+        "up" => Vector3::y(),
+        "down" => -Vector3::y(),
+        _ => bail!("expected forward, back, left, right, up or down"),
+    })
+}
+
 pub struct CefChatCommand {
     chat_command: OwnedChatCommand,
+    tab_completion: TabCompletion,
 }
 
 impl CefChatCommand {
     pub fn new() -> Self {
         Self {
             chat_command: OwnedChatCommand::new("Cef", c_chat_command_callback, false, vec!["cef"]),
+            tab_completion: TabCompletion::new(),
         }
     }
 
     pub fn initialize(&mut self) {
         self.chat_command.register();
+        self.tab_completion.initialize();
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.tab_completion.shutdown();
+    }
 }
@@ -0,0 +1,1114 @@
+use super::{permissions::Action, Chat};
+use crate::error::*;
+
+// chat messages are cut off after 255 characters
+const MAX_LINE_LENGTH: usize = 200;
+
+// typos further than this from every command aren't suggested
+const MAX_SUGGEST_DISTANCE: usize = 2;
+
+const DIRECTIONS: &[&str] = &[
+    "forward",
+    "forwards",
+    "front",
+    "back",
+    "backward",
+    "backwards",
+    "left",
+    "right",
+    "up",
+    "down",
+];
+
+const ACCESSES: &[&str] = &[
+    "anyone", "everyone", "all", "owner", "owners", "friends", "friend", "me", "self", "nobody",
+];
+
+const HIDES: &[&str] = &["newest", "new", "oldest", "old", "farthest", "furthest"];
+
+const FACE_MODES: &[&str] = &["single", "mirror", "mirrored", "double", "flat"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgKind {
+    /// any single word
+    Word,
+    /// a whole number
+    Integer,
+    Number,
+    /// on or off
+    OnOff,
+    /// one of these words
+    Choice(&'static [&'static str]),
+    /// every word left, must be last
+    Rest,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Arg {
+    /// shown in usage like `<name>`
+    pub name: &'static str,
+    pub kind: ArgKind,
+    /// only trailing args can be optional
+    pub optional: bool,
+}
+
+impl Arg {
+    const fn new(name: &'static str, kind: ArgKind) -> Self {
+        Self {
+            name,
+            kind,
+            optional: false,
+        }
+    }
+
+    const fn word(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Word)
+    }
+
+    const fn integer(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Integer)
+    }
+
+    const fn number(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Number)
+    }
+
+    const fn on_off() -> Self {
+        Self::new("on|off", ArgKind::OnOff)
+    }
+
+    const fn choice(name: &'static str, choices: &'static [&'static str]) -> Self {
+        Self::new(name, ArgKind::Choice(choices))
+    }
+
+    const fn rest(name: &'static str) -> Self {
+        Self::new(name, ArgKind::Rest)
+    }
+
+    const fn optional(self) -> Self {
+        Self {
+            name: self.name,
+            kind: self.kind,
+            optional: true,
+        }
+    }
+
+    /// if `word` could be this arg
+    pub fn accepts(&self, word: &str) -> bool {
+        match self.kind {
+            ArgKind::Word | ArgKind::Rest => true,
+            ArgKind::Integer => word.parse::<i64>().is_ok(),
+            ArgKind::Number => parse_number(word).is_ok(),
+            ArgKind::OnOff => parse_on_off(word).is_ok(),
+            ArgKind::Choice(choices) => choices.contains(&word),
+        }
+    }
+
+    fn usage(&self) -> String {
+        let name = if self.kind == ArgKind::Rest {
+            format!("{}...", self.name)
+        } else {
+            self.name.to_string()
+        };
+
+        if self.optional {
+            format!("[{}]", name)
+        } else {
+            format!("<{}>", name)
+        }
+    }
+}
+
+/// Which screen a command acts on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// doesn't act on an existing screen
+    Nothing,
+    /// the screen closest to whoever ran it
+    Closest,
+    /// the screen whose id is the first arg
+    ById,
+    /// every screen
+    All,
+}
+
+/// Who may run a command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// only us, ignored when other players say it
+    Me,
+    /// other players need our permission for `Action`
+    Action(Action),
+}
+
+/// Which arm of `command_callback` runs a command
+///
+/// Matched there without a wildcard, so adding a command without
+/// a handler doesn't compile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Handler {
+    Help,
+    Create,
+    Load,
+    Search,
+    Stop,
+    Refresh,
+    Click,
+    ClickAt,
+    Type,
+    Time,
+    Resize,
+    Accept,
+    Close,
+    CloseAll,
+    Here,
+    HereById,
+    PlaceWall,
+    Edit,
+    Snap,
+    At,
+    Angles,
+    Rotate,
+    Nudge,
+    Scale,
+    Size,
+    Transform,
+    Grow,
+    Undo,
+    Redo,
+    Name,
+    Opacity,
+    Tint,
+    Lit,
+    Sides,
+    Volume,
+    Labels,
+    Screenshot,
+    Frames,
+    Stats,
+    StatsHud,
+    Permissions,
+    Allow,
+    Friend,
+    Unfriend,
+    Block,
+    Unblock,
+    Ask,
+    Policy,
+    PolicyAllow,
+    PolicyDeny,
+    PolicyRemove,
+    PolicyMedia,
+    PolicyPrivate,
+    Limits,
+    LimitScreens,
+    LimitOwner,
+    LimitMemory,
+    LimitHide,
+}
+
+pub struct Command {
+    /// can be more than one word, like `policy allow`
+    pub name: &'static str,
+    /// other words for the name's first word
+    pub aliases: &'static [&'static str],
+    pub args: &'static [Arg],
+    pub target: Target,
+    pub permission: Permission,
+    pub help: &'static str,
+    pub handler: Handler,
+}
+
+impl Command {
+    /// if `word` is our first word or an alias for it
+    pub fn is_called(&self, word: &str) -> bool {
+        self.get_first_word() == word || self.aliases.contains(&word)
+    }
+
+    pub fn get_first_word(&self) -> &'static str {
+        self.name.split(' ').next().unwrap()
+    }
+
+    /// Our args from `words`, None if they don't fit our name and args
+    ///
+    /// `words` starts with the name.
+    pub fn match_words<'a>(&self, words: &'a [&'a str]) -> Option<&'a [&'a str]> {
+        let name_len = self.name.split(' ').count();
+        if words.len() < name_len || !self.is_called(words[0]) {
+            return None;
+        }
+
+        // the rest of a multi word name must be exact
+        if !self
+            .name
+            .split(' ')
+            .skip(1)
+            .eq(words[1..name_len].iter().copied())
+        {
+            return None;
+        }
+
+        let args = &words[name_len..];
+        if self.accepts(args) {
+            Some(args)
+        } else {
+            None
+        }
+    }
+
+    fn accepts(&self, words: &[&str]) -> bool {
+        for (i, arg) in self.args.iter().enumerate() {
+            match words.get(i) {
+                Some(word) => {
+                    if arg.kind == ArgKind::Rest {
+                        return true;
+                    }
+                    if !arg.accepts(word) {
+                        return false;
+                    }
+                }
+
+                None => {
+                    if !arg.optional {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        words.len() <= self.args.len()
+    }
+
+    pub fn usage(&self) -> String {
+        let mut usage = format!("cef {}", self.name);
+        for arg in self.args {
+            usage.push(' ');
+            usage.push_str(&arg.usage());
+        }
+        usage
+    }
+}
+
+pub static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        aliases: &[],
+        args: &[Arg::rest("command").optional()],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "list commands, or explain one",
+        handler: Handler::Help,
+    },
+    // creating and playing
+    Command {
+        name: "create",
+        aliases: &[],
+        args: &[Arg::rest("url").optional()],
+        target: Target::Nothing,
+        permission: Permission::Action(Action::Play),
+        help: "make a new screen in front of you",
+        handler: Handler::Create,
+    },
+    Command {
+        name: "load",
+        aliases: &["play"],
+        args: &[Arg::rest("url")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "play a url on the closest screen",
+        handler: Handler::Load,
+    },
+    Command {
+        name: "search",
+        aliases: &[],
+        args: &[Arg::rest("query")],
+        target: Target::Closest,
+        permission: Permission::Me,
+        help: "play the first youtube result on the closest screen",
+        handler: Handler::Search,
+    },
+    Command {
+        name: "stop",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "blank the closest screen",
+        handler: Handler::Stop,
+    },
+    Command {
+        name: "refresh",
+        aliases: &["reload"],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "reload the closest screen's page",
+        handler: Handler::Refresh,
+    },
+    Command {
+        name: "click",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "click where you're looking on the closest screen",
+        handler: Handler::Click,
+    },
+    Command {
+        name: "click",
+        aliases: &[],
+        args: &[Arg::integer("x"), Arg::integer("y")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "click a pixel on the closest screen's page",
+        handler: Handler::ClickAt,
+    },
+    Command {
+        name: "type",
+        aliases: &[],
+        args: &[Arg::rest("text")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "type into the closest screen's page",
+        handler: Handler::Type,
+    },
+    Command {
+        name: "time",
+        aliases: &["seek"],
+        args: &[Arg::word("time")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "skip to a time like 90, 1:30 or 1:02:03",
+        handler: Handler::Time,
+    },
+    Command {
+        name: "resize",
+        aliases: &[],
+        args: &[Arg::integer("width"), Arg::integer("height")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Play),
+        help: "set the closest screen's page size in pixels",
+        handler: Handler::Resize,
+    },
+    Command {
+        name: "accept",
+        aliases: &[],
+        args: &[Arg::choice("remember", &["remember"]).optional()],
+        target: Target::Closest,
+        permission: Permission::Me,
+        help: "load the url the closest screen is asking about",
+        handler: Handler::Accept,
+    },
+    // removing
+    Command {
+        name: "close",
+        aliases: &["remove", "clear"],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Close),
+        help: "remove the closest screen",
+        handler: Handler::Close,
+    },
+    Command {
+        name: "closeall",
+        aliases: &["removeall", "stopall", "clearall"],
+        args: &[],
+        target: Target::All,
+        permission: Permission::Action(Action::Close),
+        help: "remove every screen",
+        handler: Handler::CloseAll,
+    },
+    // moving
+    Command {
+        name: "here",
+        aliases: &["move"],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "move the closest screen in front of you",
+        handler: Handler::Here,
+    },
+    Command {
+        name: "here",
+        aliases: &[],
+        args: &[Arg::integer("id")],
+        target: Target::ById,
+        permission: Permission::Action(Action::Move),
+        help: "move a screen in front of you",
+        handler: Handler::HereById,
+    },
+    Command {
+        name: "place wall",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "put the closest screen flat on the wall you're looking at",
+        handler: Handler::PlaceWall,
+    },
+    Command {
+        name: "edit",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Me,
+        help: "move the closest screen with your keyboard",
+        handler: Handler::Edit,
+    },
+    Command {
+        name: "snap",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "line the closest screen up with the block grid",
+        handler: Handler::Snap,
+    },
+    Command {
+        name: "at",
+        aliases: &["tp"],
+        args: &[Arg::number("x"), Arg::number("y"), Arg::number("z")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "move the closest screen to a position",
+        handler: Handler::At,
+    },
+    Command {
+        name: "angles",
+        aliases: &["angle"],
+        args: &[
+            Arg::number("pitch"),
+            Arg::number("yaw"),
+            Arg::number("roll").optional(),
+        ],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "turn the closest screen to these angles",
+        handler: Handler::Angles,
+    },
+    Command {
+        name: "rotate",
+        aliases: &[],
+        args: &[
+            Arg::choice("pitch|yaw|roll", &["pitch", "yaw", "roll"]),
+            Arg::number("degrees"),
+        ],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "turn the closest screen by some degrees",
+        handler: Handler::Rotate,
+    },
+    Command {
+        name: "nudge",
+        aliases: &[],
+        args: &[Arg::choice("direction", DIRECTIONS), Arg::number("amount")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "move the closest screen forward, back, left, right, up or down",
+        handler: Handler::Nudge,
+    },
+    Command {
+        name: "scale",
+        aliases: &[],
+        args: &[Arg::number("scale")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "set the closest screen's scale",
+        handler: Handler::Scale,
+    },
+    Command {
+        name: "size",
+        aliases: &[],
+        args: &[Arg::number("width"), Arg::number("height")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "set the closest screen's size in blocks",
+        handler: Handler::Size,
+    },
+    Command {
+        name: "transform",
+        aliases: &[],
+        args: &[
+            Arg::integer("id"),
+            Arg::number("x"),
+            Arg::number("y"),
+            Arg::number("z"),
+            Arg::number("pitch"),
+            Arg::number("yaw"),
+            Arg::number("roll"),
+            Arg::number("width"),
+            Arg::number("height"),
+        ],
+        target: Target::ById,
+        permission: Permission::Action(Action::Move),
+        help: "set a screen's position, angles and size at once, sent by cef edit",
+        handler: Handler::Transform,
+    },
+    Command {
+        name: "grow",
+        aliases: &[],
+        args: &[Arg::number("factor")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "multiply the closest screen's size",
+        handler: Handler::Grow,
+    },
+    Command {
+        name: "undo",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "undo the closest screen's last move",
+        handler: Handler::Undo,
+    },
+    Command {
+        name: "redo",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "redo the closest screen's last undone move",
+        handler: Handler::Redo,
+    },
+    // looks
+    Command {
+        name: "name",
+        aliases: &[],
+        args: &[Arg::rest("name").optional()],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "label the closest screen, or clear its label",
+        handler: Handler::Name,
+    },
+    Command {
+        name: "opacity",
+        aliases: &[],
+        args: &[Arg::number("opacity")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "set the closest screen's opacity from 0 to 1",
+        handler: Handler::Opacity,
+    },
+    Command {
+        name: "tint",
+        aliases: &["color"],
+        args: &[Arg::word("color")],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "tint the closest screen a hex color like ff8080",
+        handler: Handler::Tint,
+    },
+    Command {
+        name: "lit",
+        aliases: &[],
+        args: &[Arg::on_off()],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "if the closest screen is darker in shadows",
+        handler: Handler::Lit,
+    },
+    Command {
+        name: "sides",
+        aliases: &["faces"],
+        args: &[
+            Arg::choice("single|mirror|flat", FACE_MODES),
+            Arg::word("color").optional(),
+        ],
+        target: Target::Closest,
+        permission: Permission::Action(Action::Move),
+        help: "how the back of the closest screen looks",
+        handler: Handler::Sides,
+    },
+    // our settings
+    Command {
+        name: "volume",
+        aliases: &[],
+        args: &[Arg::number("0-100")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "set the volume of every screen from 0 to 100",
+        handler: Handler::Volume,
+    },
+    Command {
+        name: "labels",
+        aliases: &[],
+        args: &[Arg::on_off().optional()],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "show names above screens",
+        handler: Handler::Labels,
+    },
+    Command {
+        name: "screenshot",
+        aliases: &[],
+        args: &[Arg::rest("path").optional()],
+        target: Target::Closest,
+        permission: Permission::Me,
+        help: "save the closest screen as a png",
+        handler: Handler::Screenshot,
+    },
+    Command {
+        name: "frames",
+        aliases: &[],
+        args: &[],
+        target: Target::Closest,
+        permission: Permission::Me,
+        help: "show how often the closest screen paints",
+        handler: Handler::Frames,
+    },
+    Command {
+        name: "stats",
+        aliases: &[],
+        args: &[],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "show browser and task stats",
+        handler: Handler::Stats,
+    },
+    Command {
+        name: "stats hud",
+        aliases: &[],
+        args: &[Arg::on_off().optional()],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "show stats in the corner of the screen",
+        handler: Handler::StatsHud,
+    },
+    // permissions
+    Command {
+        name: "permissions",
+        aliases: &[],
+        args: &[],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "show who may do what to screens",
+        handler: Handler::Permissions,
+    },
+    Command {
+        name: "allow",
+        aliases: &[],
+        args: &[
+            Arg::choice("play|move|close", &["play", "move", "close"]),
+            Arg::choice("anyone|owner|friends|me", ACCESSES),
+        ],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "set who else may play, move or close screens",
+        handler: Handler::Allow,
+    },
+    Command {
+        name: "friend",
+        aliases: &[],
+        args: &[Arg::word("name")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "let a player do what friends may",
+        handler: Handler::Friend,
+    },
+    Command {
+        name: "unfriend",
+        aliases: &[],
+        args: &[Arg::word("name")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "remove a player from friends",
+        handler: Handler::Unfriend,
+    },
+    Command {
+        name: "block",
+        aliases: &["ignore"],
+        args: &[Arg::word("name")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "ignore a player's cef commands",
+        handler: Handler::Block,
+    },
+    Command {
+        name: "unblock",
+        aliases: &["unignore"],
+        args: &[Arg::word("name")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "stop ignoring a player",
+        handler: Handler::Unblock,
+    },
+    Command {
+        name: "ask",
+        aliases: &[],
+        args: &[Arg::on_off()],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "ask before loading other players' urls",
+        handler: Handler::Ask,
+    },
+    Command {
+        name: "policy",
+        aliases: &[],
+        args: &[],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "show which urls may be loaded",
+        handler: Handler::Policy,
+    },
+    Command {
+        name: "policy allow",
+        aliases: &[],
+        args: &[Arg::word("pattern")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "always allow urls matching a pattern",
+        handler: Handler::PolicyAllow,
+    },
+    Command {
+        name: "policy deny",
+        aliases: &[],
+        args: &[Arg::word("pattern")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "never load urls matching a pattern",
+        handler: Handler::PolicyDeny,
+    },
+    Command {
+        name: "policy remove",
+        aliases: &[],
+        args: &[Arg::word("pattern")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "forget an allowed or denied pattern",
+        handler: Handler::PolicyRemove,
+    },
+    Command {
+        name: "policy media",
+        aliases: &[],
+        args: &[Arg::on_off()],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "only load youtube and media files",
+        handler: Handler::PolicyMedia,
+    },
+    Command {
+        name: "policy private",
+        aliases: &[],
+        args: &[Arg::on_off()],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "block urls on private networks",
+        handler: Handler::PolicyPrivate,
+    },
+    // limits
+    Command {
+        name: "limits",
+        aliases: &[],
+        args: &[],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "show screen limits",
+        handler: Handler::Limits,
+    },
+    Command {
+        name: "limit screens",
+        aliases: &[],
+        args: &[Arg::integer("count")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "most screens drawn at once",
+        handler: Handler::LimitScreens,
+    },
+    Command {
+        name: "limit owner",
+        aliases: &[],
+        args: &[Arg::integer("count")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "most screens drawn per player",
+        handler: Handler::LimitOwner,
+    },
+    Command {
+        name: "limit memory",
+        aliases: &[],
+        args: &[Arg::integer("megabytes")],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "most texture memory for all screens",
+        handler: Handler::LimitMemory,
+    },
+    Command {
+        name: "limit hide",
+        aliases: &[],
+        args: &[Arg::choice("newest|oldest|farthest", HIDES)],
+        target: Target::Nothing,
+        permission: Permission::Me,
+        help: "which screens to stop drawing when over the limits",
+        handler: Handler::LimitHide,
+    },
+];
+
+/// Commands with `word` as their first word or alias
+pub fn find(word: &str) -> Vec<&'static Command> {
+    COMMANDS
+        .iter()
+        .filter(|command| command.is_called(word))
+        .collect()
+}
+
+/// The command `words` runs and its args
+///
+/// Errors say how to fix the input.
+pub fn parse<'a>(words: &'a [&'a str]) -> Result<(&'static Command, &'a [&'a str])> {
+    let first = match words.first() {
+        Some(first) => *first,
+        None => bail!("usage: cef <command>, see \"cef help\""),
+    };
+
+    let candidates = find(first);
+    if candidates.is_empty() {
+        bail!(unknown_command(first));
+    }
+
+    for command in &candidates {
+        if let Some(args) = command.match_words(words) {
+            return Ok((*command, args));
+        }
+    }
+
+    let usages: Vec<_> = candidates.iter().map(|command| command.usage()).collect();
+    bail!("usage: {}", usages.join(" or "))
+}
+
+fn unknown_command(word: &str) -> String {
+    let suggestions = suggest(word);
+    if suggestions.is_empty() {
+        format!("unknown command {:?}, see \"cef help\"", word)
+    } else {
+        format!(
+            "unknown command {:?}, did you mean {}?",
+            word,
+            suggestions.join(" or ")
+        )
+    }
+}
+
+/// Names and aliases closest to a misspelled `word`
+pub fn suggest(word: &str) -> Vec<&'static str> {
+    let mut best = MAX_SUGGEST_DISTANCE + 1;
+    let mut suggestions = Vec::new();
+
+    for command in COMMANDS {
+        let names = Some(command.get_first_word())
+            .into_iter()
+            .chain(command.aliases.iter().copied());
+
+        for name in names {
+            let distance = edit_distance(word, name);
+            // replacing every letter isn't a typo
+            if distance >= name.len() || distance > best {
+                continue;
+            }
+
+            if distance < best {
+                best = distance;
+                suggestions.clear();
+            }
+            if !suggestions.contains(&name) {
+                suggestions.push(name);
+            }
+        }
+    }
+
+    suggestions
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+
+    // distances from a's prefix so far to each prefix of b
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let above = row[j + 1];
+            let cost = if a_char == *b_char { 0 } else { 1 };
+
+            row[j + 1] = (diagonal + cost).min(above + 1).min(row[j] + 1);
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Prints `cef help [command]`
+pub fn print_help(words: &[&str]) -> Result<()> {
+    let word = match words.first() {
+        Some(word) => *word,
+        None => {
+            let mut names: Vec<&str> = Vec::new();
+            for command in COMMANDS {
+                let name = command.get_first_word();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+
+            for line in wrap(&names, MAX_LINE_LENGTH) {
+                Chat::print(format!("cef commands: {}", line));
+            }
+            Chat::print("use \"cef help <command>\" for more");
+
+            return Ok(());
+        }
+    };
+
+    let commands = find(word);
+    if commands.is_empty() {
+        bail!(unknown_command(word));
+    }
+
+    for command in &commands {
+        Chat::print(format!("{} - {}", command.usage(), command.help));
+    }
+
+    let aliases: Vec<&str> = commands
+        .iter()
+        .flat_map(|command| command.aliases.iter().copied())
+        .collect();
+    if !aliases.is_empty() {
+        Chat::print(format!("also: {}", aliases.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// joins `words` with commas into lines of at most `max_length`
+fn wrap(words: &[&str], max_length: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in words {
+        if !line.is_empty() && line.len() + 2 + word.len() > max_length {
+            lines.push(line);
+            line = String::new();
+        }
+
+        if !line.is_empty() {
+            line.push_str(", ");
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// A finite number, NaN or infinity would break a screen's transform
+pub fn parse_number(word: &str) -> Result<f32> {
+    let number: f32 = word.parse()?;
+    if !number.is_finite() {
+        bail!("expected a number, not {}", word);
+    }
+
+    Ok(number)
+}
+
+pub fn parse_on_off(on_off: &str) -> Result<bool> {
+    Ok(match on_off {
+        "on" | "true" | "1" => true,
+        "off" | "false" | "0" => false,
+        _ => bail!("expected on or off"),
+    })
+}
+
+#[test]
+fn test_parse() {
+    let (command, args) = parse(&["angle", "1", "2"]).unwrap();
+    assert_eq!(command.name, "angles");
+    assert_eq!(args, &["1", "2"]);
+
+    let (command, args) = parse(&["here", "5"]).unwrap();
+    assert_eq!(command.target, Target::ById);
+    assert_eq!(args, &["5"]);
+
+    let (command, args) = parse(&["policy", "allow", "*.example.com"]).unwrap();
+    assert_eq!(command.name, "policy allow");
+    assert_eq!(args, &["*.example.com"]);
+
+    let (command, args) = parse(&["create", "https://a", "b"]).unwrap();
+    assert_eq!(command.name, "create");
+    assert_eq!(args.len(), 2);
+    assert!(parse(&["create"]).is_ok());
+
+    // bad args give usage
+    let e = parse(&["at", "1", "two", "3"]).unwrap_err();
+    assert_eq!(e.to_string(), "usage: cef at <x> <y> <z>");
+    let e = parse(&["click", "1"]).unwrap_err();
+    assert_eq!(e.to_string(), "usage: cef click or cef click <x> <y>");
+    assert!(parse(&["lit", "maybe"]).is_err());
+    assert!(parse(&["stop", "now"]).is_err());
+    assert!(parse(&["load"]).is_err());
+    assert!(parse(&["policy", "nope", "x"]).is_err());
+    assert!(parse(&[]).is_err());
+
+    // these would break the screen's transform for everyone it's synced to
+    assert!(parse(&["size", "NaN", "1"]).is_err());
+    assert!(parse(&["at", "inf", "0", "0"]).is_err());
+    assert!(parse(&["grow", "-infinity"]).is_err());
+    assert!(parse(&["opacity", "0.5"]).is_ok());
+
+    let e = parse(&["crate"]).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "unknown command \"crate\", did you mean create?"
+    );
+}
+
+#[test]
+fn test_suggest() {
+    assert_eq!(suggest("screnshot"), vec!["screenshot"]);
+    assert_eq!(suggest("sise"), vec!["size"]);
+    assert_eq!(suggest("serch"), vec!["search"]);
+    assert!(suggest("xyzzy").is_empty());
+
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("same", "same"), 0);
+}
+
+#[test]
+fn test_permission() {
+    // what other players need to run these
+    let action = |word| match find(word)[0].permission {
+        Permission::Action(action) => Some(action),
+        Permission::Me => None,
+    };
+
+    assert_eq!(action("play"), Some(Action::Play));
+    assert_eq!(action("closeall"), Some(Action::Close));
+    assert_eq!(action("at"), Some(Action::Move));
+    assert_eq!(action("search"), None);
+
+    // Rest takes every word left so it has to be last
+    for command in COMMANDS {
+        assert!(!command.help.is_empty());
+        let last = command
+            .args
+            .iter()
+            .position(|arg| arg.kind == ArgKind::Rest);
+        if let Some(last) = last {
+            assert_eq!(last, command.args.len() - 1, "{}", command.name);
+        }
+    }
+}
+
+#[test]
+fn test_wrap() {
+    assert_eq!(wrap(&["aa", "bb", "cc"], 6), vec!["aa, bb", "cc"]);
+    assert!(wrap(&[], 6).is_empty());
+}
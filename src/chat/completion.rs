@@ -0,0 +1,661 @@
+use super::commands::{self, ArgKind, Command, Target, COMMANDS};
+use crate::entity_manager::{
+    overlay::{self, Overlay},
+    EntityManager,
+};
+use classicube_helpers::events::input::{InputDownEvent, InputDownEventHandler};
+use classicube_sys::{
+    Chat_AddOf, GuiPriority_GUI_PRIORITY_CHAT, Gui_GetInputGrab, KeyBind_, KeyBind__KEYBIND_CHAT,
+    KeyBind__KEYBIND_SEND_CHAT, KeyBinds, Key_, Key_Pressed, Key__KEY_BACKSPACE, Key__KEY_DELETE,
+    Key__KEY_DOWN, Key__KEY_END, Key__KEY_HOME, Key__KEY_LCTRL, Key__KEY_LEFT, Key__KEY_RCTRL,
+    Key__KEY_RIGHT, Key__KEY_SLASH, Key__KEY_TAB, Key__KEY_UP, Key__KEY_V,
+    MsgType_MSG_TYPE_CLIENTSTATUS_2, OwnedString, ScreenVTABLE, String as CcString,
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    os::raw::{c_char, c_int, c_void},
+};
+
+// how many urls we remember for completing
+const MAX_RECENT_URLS: usize = 10;
+
+// the game's input holds at most 4 lines of 64
+const MAX_INPUT_LENGTH: usize = 256;
+
+const PREFIX: &str = "/client cef ";
+
+thread_local!(
+    static RECENT_URLS: RefCell<VecDeque<String>> = RefCell::new(VecDeque::new());
+);
+
+// Some while chat is open
+thread_local!(
+    static INPUT: RefCell<Option<ChatInput>> = RefCell::new(None);
+);
+
+thread_local!(
+    static CYCLE: RefCell<Option<Cycle>> = RefCell::new(None);
+);
+
+// sees chat's keys before chat does while it's open
+thread_local!(
+    static OVERLAY: RefCell<Option<Overlay>> = RefCell::new(None);
+);
+
+static V_TABLE: ScreenVTABLE = ScreenVTABLE {
+    Init: Some(overlay::no_op),
+    Update: Some(overlay::no_op_delta),
+    Free: Some(overlay::no_op),
+    Render: Some(overlay::no_op_delta),
+    BuildMesh: Some(overlay::no_op),
+    HandlesInputDown: Some(handles_input_down),
+    OnInputUp: Some(overlay::no_op_key),
+    HandlesKeyPress: Some(handles_key_press),
+    HandlesTextChanged: Some(handles_text_changed),
+    HandlesPointerDown: Some(handles_pointer_down),
+    OnPointerUp: Some(overlay::no_op_pointer),
+    HandlesPointerMove: Some(overlay::ignore_pointer),
+    HandlesMouseScroll: Some(overlay::ignore_scroll),
+    Layout: Some(overlay::no_op),
+    ContextLost: Some(overlay::no_op),
+    ContextRecreated: Some(overlay::no_op),
+};
+
+/// returning 1 for tab keeps the game from completing player names in our
+/// commands, everything else goes on to chat
+unsafe extern "C" fn handles_input_down(_elem: *mut c_void, key: c_int) -> c_int {
+    let ctrl =
+        Key_Pressed[Key__KEY_LCTRL as usize] != 0 || Key_Pressed[Key__KEY_RCTRL as usize] != 0;
+
+    #[allow(non_upper_case_globals)]
+    match key as Key_ {
+        Key__KEY_TAB => return TabCompletion::on_tab() as c_int,
+
+        // ctrl jumps or deletes whole words
+        Key__KEY_BACKSPACE if !ctrl => with_input(|input| input.backspace()),
+        Key__KEY_DELETE if !ctrl => with_input(|input| input.delete()),
+        Key__KEY_LEFT if !ctrl => with_input(|input| input.move_caret(-1)),
+        Key__KEY_RIGHT if !ctrl => with_input(|input| input.move_caret(1)),
+        Key__KEY_HOME => with_input(|input| input.move_caret(-(MAX_INPUT_LENGTH as isize))),
+        Key__KEY_END => with_input(|input| input.move_caret(MAX_INPUT_LENGTH as isize)),
+
+        // history, pasting and whole word edits
+        Key__KEY_UP | Key__KEY_DOWN | Key__KEY_BACKSPACE | Key__KEY_DELETE | Key__KEY_LEFT
+        | Key__KEY_RIGHT => with_input(|input| input.lose()),
+        Key__KEY_V if ctrl => with_input(|input| input.lose()),
+
+        _ => {}
+    }
+
+    0
+}
+
+unsafe extern "C" fn handles_key_press(_elem: *mut c_void, key_char: c_char) -> c_int {
+    with_input(|input| input.press(key_char as u8 as char));
+
+    0
+}
+
+/// clicking moves the caret or selects text, which we can't follow
+unsafe extern "C" fn handles_pointer_down(
+    _elem: *mut c_void,
+    _id: c_int,
+    _x: c_int,
+    _y: c_int,
+) -> c_int {
+    with_input(|input| input.lose());
+
+    0
+}
+
+/// the on-screen keyboard replaced the text
+unsafe extern "C" fn handles_text_changed(_elem: *mut c_void, _text: *const CcString) -> c_int {
+    with_input(|input| input.lose());
+
+    0
+}
+
+fn with_input<F: FnOnce(&mut ChatInput)>(f: F) {
+    INPUT.with(|cell| {
+        if let Some(input) = &mut *cell.borrow_mut() {
+            f(input);
+        }
+    });
+}
+
+/// What's in chat's input, followed from the keys that reach it
+///
+/// The game doesn't let us read it.
+struct ChatInput {
+    text: Vec<char>,
+    caret: usize,
+    /// a key we can't follow changed the text
+    lost: bool,
+    /// opening chat drops the next character, like the key that opened it
+    suppress_next_press: bool,
+}
+
+impl ChatInput {
+    fn new(text: &str) -> Self {
+        let text: Vec<char> = text.chars().take(MAX_INPUT_LENGTH).collect();
+
+        Self {
+            caret: text.len(),
+            text,
+            lost: false,
+            suppress_next_press: true,
+        }
+    }
+
+    /// The text, None if we lost track of it
+    fn get_text(&self) -> Option<String> {
+        if self.lost {
+            None
+        } else {
+            Some(self.text.iter().collect())
+        }
+    }
+
+    fn is_caret_at_end(&self) -> bool {
+        self.caret == self.text.len()
+    }
+
+    /// How many backspaces and then which characters turn the text into `new`
+    fn get_edit(&self, new: &str) -> (usize, Vec<char>) {
+        let same = self
+            .text
+            .iter()
+            .zip(new.chars())
+            .take_while(|(old, new)| *old == new)
+            .count();
+
+        (self.text.len() - same, new.chars().skip(same).collect())
+    }
+
+    fn press(&mut self, c: char) {
+        if self.suppress_next_press {
+            self.suppress_next_press = false;
+            return;
+        }
+
+        if c.is_ascii_control() {
+            // the game doesn't type these either
+        } else if !c.is_ascii() {
+            // the game's codepage isn't ours
+            self.lose();
+        } else if self.text.len() < MAX_INPUT_LENGTH {
+            self.text.insert(self.caret, c);
+            self.caret += 1;
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.caret > 0 {
+            self.caret -= 1;
+            self.text.remove(self.caret);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.caret < self.text.len() {
+            self.text.remove(self.caret);
+        }
+    }
+
+    fn move_caret(&mut self, amount: isize) {
+        let caret = self.caret as isize + amount;
+        self.caret = caret.max(0).min(self.text.len() as isize) as usize;
+    }
+
+    fn lose(&mut self) {
+        self.lost = true;
+        self.text.clear();
+        self.caret = 0;
+    }
+}
+
+/// Tab presses in a row cycle through the same candidates
+struct Cycle {
+    /// the input before the word being completed
+    prefix: String,
+    candidates: Vec<String>,
+    index: usize,
+    /// the input after our last completion
+    completed: String,
+}
+
+impl Cycle {
+    fn get_input(&self) -> String {
+        format!("{}{}", self.prefix, self.candidates[self.index])
+    }
+}
+
+/// Completes `/client cef` commands when tab is pressed in chat
+///
+/// While chat is open a screen above it follows what's typed and takes tab
+/// from the game for our commands. The input is edited by sending chat the
+/// backspaces and characters it needs. Keys we can't follow, like history,
+/// pasting or clicking, turn completing off until chat is opened again.
+pub struct TabCompletion {
+    input_down_handler: InputDownEventHandler,
+}
+
+impl TabCompletion {
+    pub fn new() -> Self {
+        Self {
+            input_down_handler: InputDownEventHandler::new(),
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        OVERLAY.with(|cell| {
+            *cell.borrow_mut() = Some(Overlay::with_priority(
+                &V_TABLE,
+                GuiPriority_GUI_PRIORITY_CHAT as c_int + 1,
+            ));
+        });
+
+        // runs after the game handled the key, so chat has opened or closed
+        self.input_down_handler
+            .on(|InputDownEvent { key, repeating: _ }| {
+                let is_open = unsafe { !Gui_GetInputGrab().is_null() };
+                let was_open = INPUT.with(|cell| cell.borrow().is_some());
+
+                if is_open && !was_open && is_chat_key(*key) {
+                    Self::on_open(*key);
+                } else if !is_open && was_open {
+                    Self::on_close();
+                }
+            });
+    }
+
+    pub fn shutdown(&mut self) {
+        Self::on_close();
+
+        OVERLAY.with(|cell| {
+            cell.borrow_mut().take();
+        });
+    }
+
+    fn on_open(key: Key_) {
+        let text = if key == Key__KEY_SLASH { "/" } else { "" };
+        INPUT.with(|cell| *cell.borrow_mut() = Some(ChatInput::new(text)));
+
+        OVERLAY.with(|cell| {
+            if let Some(overlay) = &mut *cell.borrow_mut() {
+                overlay.add();
+            }
+        });
+    }
+
+    fn on_close() {
+        OVERLAY.with(|cell| {
+            if let Some(overlay) = &mut *cell.borrow_mut() {
+                overlay.remove();
+            }
+        });
+
+        INPUT.with(|cell| cell.borrow_mut().take());
+
+        let completing = CYCLE.with(|cell| cell.borrow_mut().take()).is_some();
+        if completing {
+            set_status("");
+        }
+    }
+
+    /// If tab was ours
+    fn on_tab() -> bool {
+        let input = INPUT.with(|cell| {
+            let input = cell.borrow();
+            let input = input.as_ref()?;
+
+            Some((input.get_text()?, input.is_caret_at_end()))
+        });
+
+        let input = match input {
+            Some((input, true)) if has_prefix(&input) => input,
+            // we only complete the end
+            Some((input, false)) if has_prefix(&input) => return true,
+            _ => return false,
+        };
+
+        let maybe_completed = CYCLE.with(|cell| {
+            let cycle = &mut *cell.borrow_mut();
+            let completed = tab(cycle, &input)?;
+
+            if let Some(cycle) = cycle {
+                if cycle.candidates.len() > 1 {
+                    set_status(&format!(
+                        "&e{}",
+                        describe_candidates(&cycle.candidates, cycle.index)
+                    ));
+                }
+            }
+
+            Some(completed)
+        });
+
+        if let Some(completed) = maybe_completed {
+            let maybe_edit = INPUT.with(|cell| {
+                let input = cell.borrow();
+                input.as_ref().map(|input| input.get_edit(&completed))
+            });
+
+            if let Some((backspaces, chars)) = maybe_edit {
+                type_into_chat(backspaces, &chars);
+
+                with_input(|input| {
+                    for _ in 0..backspaces {
+                        input.backspace();
+                    }
+                    for &c in &chars {
+                        input.press(c);
+                    }
+                });
+            }
+        }
+
+        true
+    }
+}
+
+/// Edits chat's input with its own key handlers
+///
+/// `ChatScreen_OpenInput` would make chat drop the next character typed.
+fn type_into_chat(backspaces: usize, chars: &[char]) {
+    unsafe {
+        // chat, we only get keys while it's open with nothing above it
+        let screen = Gui_GetInputGrab();
+        if screen.is_null() {
+            return;
+        }
+
+        let v_table = &*(*screen).VTABLE;
+        let elem = screen as *mut c_void;
+
+        if let Some(handles_input_down) = v_table.HandlesInputDown {
+            for _ in 0..backspaces {
+                handles_input_down(elem, Key__KEY_BACKSPACE as c_int);
+            }
+        }
+
+        if let Some(handles_key_press) = v_table.HandlesKeyPress {
+            for &c in chars {
+                handles_key_press(elem, c as u8 as c_char);
+            }
+        }
+    }
+}
+
+/// If `key` opens chat
+fn is_chat_key(key: Key_) -> bool {
+    let get_bind = |bind: KeyBind_| unsafe { KeyBinds[bind as usize] as Key_ };
+
+    key == Key__KEY_SLASH
+        || key == get_bind(KeyBind__KEYBIND_CHAT)
+        || key == get_bind(KeyBind__KEYBIND_SEND_CHAT)
+}
+
+fn has_prefix(input: &str) -> bool {
+    input
+        .get(..PREFIX.len())
+        .map_or(false, |start| start.eq_ignore_ascii_case(PREFIX))
+}
+
+fn set_status(text: &str) {
+    let owned_string = OwnedString::new(text);
+    unsafe {
+        Chat_AddOf(
+            owned_string.as_cc_string(),
+            MsgType_MSG_TYPE_CLIENTSTATUS_2 as c_int,
+        );
+    }
+}
+
+/// candidates with the current one highlighted
+fn describe_candidates(candidates: &[String], index: usize) -> String {
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| {
+            if i == index {
+                format!("&f{}&e", candidate)
+            } else {
+                candidate.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Remembers a url we played so tab can complete it later
+pub fn remember_url(url: &str) {
+    RECENT_URLS.with(|cell| {
+        let urls = &mut *cell.borrow_mut();
+        urls.retain(|u| u != url);
+        urls.push_front(url.to_string());
+        urls.truncate(MAX_RECENT_URLS);
+    });
+}
+
+/// The input after pressing tab on `input`, None if there's nothing to complete
+///
+/// Pressing tab again on our own completion moves to the next candidate.
+fn tab(cycle: &mut Option<Cycle>, input: &str) -> Option<String> {
+    if let Some(cycle) = cycle {
+        if cycle.completed == input {
+            cycle.index = (cycle.index + 1) % cycle.candidates.len();
+            cycle.completed = cycle.get_input();
+            return Some(cycle.completed.clone());
+        }
+    }
+
+    if !has_prefix(input) {
+        *cycle = None;
+        return None;
+    }
+
+    let split = input.rfind(' ').unwrap() + 1;
+    let (prefix, partial) = input.split_at(split);
+    let words: Vec<&str> = prefix[PREFIX.len()..].split_whitespace().collect();
+
+    let candidates = complete(&words, partial);
+    if candidates.is_empty() {
+        *cycle = None;
+        return None;
+    }
+
+    let mut new_cycle = Cycle {
+        prefix: prefix.to_string(),
+        candidates,
+        index: 0,
+        completed: String::new(),
+    };
+    new_cycle.completed = new_cycle.get_input();
+
+    let completed = new_cycle.completed.clone();
+    *cycle = Some(new_cycle);
+
+    Some(completed)
+}
+
+/// Words that could replace `partial` after the `cef` args `words`
+pub fn complete(words: &[&str], partial: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    let mut add = |candidate: &str| {
+        if candidate.starts_with(partial) && !candidates.iter().any(|c| c == candidate) {
+            candidates.push(candidate.to_string());
+        }
+    };
+
+    let first = match words.first() {
+        Some(first) => *first,
+        None => {
+            for command in COMMANDS {
+                add(command.get_first_word());
+                for alias in command.aliases {
+                    add(alias);
+                }
+            }
+
+            return candidates;
+        }
+    };
+
+    for command in commands::find(first) {
+        let name: Vec<&str> = command.name.split(' ').collect();
+
+        // still typing a multi word name
+        if words.len() < name.len() {
+            if name[1..words.len()] == words[1..] {
+                add(name[words.len()]);
+            }
+            continue;
+        }
+
+        if name[1..] != words[1..name.len()] {
+            continue;
+        }
+
+        if let Some(arg) = command.args.get(words.len() - name.len()) {
+            for candidate in get_arg_candidates(command, arg.kind, arg.name) {
+                add(&candidate);
+            }
+        }
+    }
+
+    candidates
+}
+
+fn get_arg_candidates(command: &Command, kind: ArgKind, name: &str) -> Vec<String> {
+    match kind {
+        ArgKind::Choice(choices) => choices.iter().map(|s| s.to_string()).collect(),
+
+        ArgKind::OnOff => vec!["on".to_string(), "off".to_string()],
+
+        _ if command.target == Target::ById => get_screen_ids(),
+
+        _ if name == "url" => RECENT_URLS.with(|cell| cell.borrow().iter().cloned().collect()),
+
+        _ => Vec::new(),
+    }
+}
+
+/// ids of our screens, named ones first
+fn get_screen_ids() -> Vec<String> {
+    let mut screens: Vec<(usize, bool)> = EntityManager::with_all_entities(|entities| {
+        entities
+            .values()
+            .map(|entity| (entity.id, entity.get_name().is_some()))
+            .collect()
+    });
+    screens.sort_by_key(|&(id, named)| (!named, id));
+
+    screens.iter().map(|(id, _named)| id.to_string()).collect()
+}
+
+#[test]
+fn test_complete() {
+    assert_eq!(complete(&[], "se"), vec!["search", "seek", "screenshot"]);
+    assert_eq!(
+        complete(&["policy"], ""),
+        vec!["allow", "deny", "remove", "media", "private"]
+    );
+    assert_eq!(complete(&["stats"], "h"), vec!["hud"]);
+    assert_eq!(complete(&["stats", "hud"], "o"), vec!["on", "off"]);
+    assert_eq!(complete(&["rotate"], "y"), vec!["yaw"]);
+    assert_eq!(complete(&["allow", "move"], "f"), vec!["friends", "friend"]);
+    assert!(complete(&["volume"], "").is_empty());
+    assert!(complete(&["nope"], "").is_empty());
+
+    remember_url("https://example.com/a");
+    remember_url("https://example.com/b");
+    remember_url("https://example.com/a");
+    assert_eq!(
+        complete(&["play"], "https://"),
+        vec!["https://example.com/a", "https://example.com/b"]
+    );
+}
+
+#[test]
+fn test_tab() {
+    let mut cycle = None;
+
+    assert_eq!(
+        tab(&mut cycle, "/client cef se").as_deref(),
+        Some("/client cef search")
+    );
+    assert_eq!(
+        tab(&mut cycle, "/client cef search").as_deref(),
+        Some("/client cef seek")
+    );
+    assert_eq!(
+        tab(&mut cycle, "/client cef seek").as_deref(),
+        Some("/client cef screenshot")
+    );
+    assert_eq!(
+        tab(&mut cycle, "/client cef screenshot").as_deref(),
+        Some("/client cef search")
+    );
+
+    // typing something else starts over
+    assert_eq!(
+        tab(&mut cycle, "/Client cef lit o").as_deref(),
+        Some("/Client cef lit on")
+    );
+    assert_eq!(tab(&mut cycle, "/client cef xyz"), None);
+    assert_eq!(tab(&mut cycle, "hello se"), None);
+    assert!(cycle.is_none());
+}
+
+#[test]
+fn test_chat_input() {
+    let mut input = ChatInput::new("/");
+    // the key that opened chat
+    input.press('/');
+    for c in "client cef pla".chars() {
+        input.press(c);
+    }
+    assert_eq!(input.get_text().as_deref(), Some("/client cef pla"));
+
+    input.move_caret(-3);
+    input.press('x');
+    input.backspace();
+    input.delete();
+    assert_eq!(input.get_text().as_deref(), Some("/client cef la"));
+    assert!(!input.is_caret_at_end());
+
+    input.move_caret(MAX_INPUT_LENGTH as isize);
+    assert!(input.is_caret_at_end());
+
+    for _ in 0..(2 * MAX_INPUT_LENGTH) {
+        input.press('a');
+    }
+    assert_eq!(input.text.len(), MAX_INPUT_LENGTH);
+
+    input.lose();
+    input.press('a');
+    assert_eq!(input.get_text(), None);
+}
+
+#[test]
+fn test_get_edit() {
+    let mut input = ChatInput::new("");
+    // the key that opened chat
+    input.press('t');
+    for c in "/client cef se".chars() {
+        input.press(c);
+    }
+
+    assert_eq!(
+        input.get_edit("/client cef search"),
+        (0, "arch".chars().collect())
+    );
+    // the next candidate replaces the last one
+    assert_eq!(
+        input.get_edit("/client cef screenshot"),
+        (1, "creenshot".chars().collect())
+    );
+    assert_eq!(input.get_edit("/client cef se"), (0, Vec::new()));
+}
@@ -1,12 +1,13 @@
 use super::{wait_for_message, SHOULD_BLOCK};
 use crate::{
     async_manager::AsyncManager,
-    chat::{hidden_communication::whispers::start_whispering, Chat, TAB_LIST},
+    chat::{hidden_communication::whispers::start_whispering, Chat},
     error::*,
+    host,
     plugin::APP_NAME,
 };
 use async_std::future::timeout;
-use classicube_helpers::{tab_list::remove_color, CellGetSet, OptionWithInner};
+use classicube_helpers::{tab_list::remove_color, CellGetSet};
 use classicube_sys::ENTITIES_SELF_ID;
 use futures::{future::RemoteHandle, prelude::*};
 use log::{debug, warn};
@@ -145,17 +146,12 @@ async fn process_clients_response(messages: Vec<String>) -> Result<()> {
     let players_with_cef: Vec<(u8, String)> = names_with_cef
         .drain(..)
         .filter_map(|name| {
-            TAB_LIST.with_inner(|tab_list| {
-                tab_list.find_entry_by_nick_name(&name).and_then(|entry| {
-                    let id = entry.get_id();
-                    if id == ENTITIES_SELF_ID as u8 {
-                        None
-                    } else {
-                        let real_name = entry.get_real_name()?;
-                        Some((id, real_name))
-                    }
-                })
-            })?
+            let entry = host::get().find_tab_entry(&name)?;
+            if entry.id == ENTITIES_SELF_ID as u8 {
+                None
+            } else {
+                Some((entry.id, entry.real_name))
+            }
         })
         .collect();
 
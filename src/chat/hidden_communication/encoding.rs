@@ -1,8 +1,11 @@
 use crate::{
-    entity_manager::EntityManager,
+    cef::RustRefBrowser,
+    entity_manager::{EntityManager, FaceMode, MODEL_HEIGHT, MODEL_WIDTH},
     error::*,
-    players::{Player, PlayerTrait},
+    players::{Player, PlayerState, PlayerTrait, YoutubePlayer},
 };
+use async_std::future::timeout;
+use futures::future;
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -12,8 +15,15 @@ pub struct LightEntity {
     pub id: usize,
     pub player: Player,
     pub pos: [f32; 3],
-    pub ang: [f32; 2],
-    pub scale: f32,
+    /// pitch, yaw, roll
+    pub ang: [f32; 3],
+    pub size: [f32; 2],
+    pub opacity: f32,
+    pub tint: [u8; 3],
+    pub lit: bool,
+    pub face_mode: FaceMode,
+    pub name: Option<String>,
+    pub owner: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,9 +31,58 @@ pub struct Message {
     pub entities: Vec<LightEntity>,
 }
 
+// Messages start with this and a version byte. Released clients send
+// messages without it, which start with the entity count as a u64 and
+// can't have these bytes at the front.
+const MESSAGE_MAGIC: &[u8] = b"CEF";
+
+const MESSAGE_VERSION: u8 = 1;
+
+// how long a reply waits for youtube pages to tell us their time
+const YOUTUBE_TIME_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// LightEntity as sent by released clients
+#[derive(Debug, Serialize, Deserialize)]
+struct LightEntityV0 {
+    id: usize,
+    player: Player,
+    pos: [f32; 3],
+    ang: [f32; 2],
+    scale: f32,
+}
+
+impl From<LightEntityV0> for LightEntity {
+    fn from(old: LightEntityV0) -> Self {
+        Self {
+            id: old.id,
+            player: old.player,
+            pos: old.pos,
+            ang: angles_without_roll(old.ang),
+            size: size_from_scale(old.scale),
+            opacity: 1.0,
+            tint: [255, 255, 255],
+            lit: false,
+            face_mode: FaceMode::default(),
+            name: None,
+            owner: None,
+        }
+    }
+}
+
+/// old screens were scaled equally in both directions
+fn size_from_scale(scale: f32) -> [f32; 2] {
+    [scale * MODEL_WIDTH as f32, scale * MODEL_HEIGHT as f32]
+}
+
+fn angles_without_roll([pitch, yaw]: [f32; 2]) -> [f32; 3] {
+    [pitch, yaw, 0.0]
+}
+
 /// to base64
 pub fn encode(message: &Message) -> Result<String> {
-    let data = bincode::serialize(message)?;
+    let mut data = MESSAGE_MAGIC.to_vec();
+    data.push(MESSAGE_VERSION);
+    data.append(&mut bincode::serialize(message)?);
 
     Ok(base64::encode(data))
 }
@@ -32,19 +91,49 @@ pub fn encode(message: &Message) -> Result<String> {
 pub fn decode<T: AsRef<[u8]>>(input: T) -> Result<Message> {
     let data = base64::decode(input)?;
 
-    Ok(bincode::deserialize(&data)?)
+    let (version, data) = if data.starts_with(MESSAGE_MAGIC) && data.len() > MESSAGE_MAGIC.len() {
+        (data[MESSAGE_MAGIC.len()], &data[MESSAGE_MAGIC.len() + 1..])
+    } else {
+        (0, &data[..])
+    };
+
+    match version {
+        MESSAGE_VERSION => Ok(bincode::deserialize(data)?),
+        0 => decode_old::<LightEntityV0>(data),
+        _ => bail!("unsupported message version {}", version),
+    }
 }
 
-pub fn create_message() -> Message {
+/// A message from an older version, with its entities brought up to date
+fn decode_old<'a, T>(data: &'a [u8]) -> Result<Message>
+where
+    T: Deserialize<'a> + Into<LightEntity>,
+{
+    // a message is just its entity list
+    let entities: Vec<T> = bincode::deserialize(data)?;
+
+    Ok(Message {
+        entities: entities.into_iter().map(Into::into).collect(),
+    })
+}
+
+pub async fn create_message() -> Message {
     let mut light_entities = Vec::new();
+    let mut youtube_browsers = Vec::new();
 
     EntityManager::with_all_entities(|entities| {
         for (&id, entity) in entities {
             let e = &entity.entity;
 
             let pos = [e.Position.X, e.Position.Y, e.Position.Z];
-            let ang = [e.RotX, e.RotY];
-            let scale = entity.get_scale();
+            let ang = entity.get_angles();
+            let size = entity.get_size();
+            let opacity = entity.get_opacity();
+            let tint = entity.get_tint();
+            let lit = entity.get_lit();
+            let face_mode = entity.get_face_mode();
+            let name = entity.get_name().map(|s| s.to_string());
+            let owner = entity.get_owner().map(|s| s.to_string());
 
             let mut player = entity.player.clone();
 
@@ -58,22 +147,78 @@ pub fn create_message() -> Message {
                 }
             }
 
+            if let (Player::Youtube(_), Some(browser)) = (&player, &entity.browser) {
+                youtube_browsers.push((light_entities.len(), browser.clone()));
+            }
+
             light_entities.push(LightEntity {
                 id,
                 pos,
                 ang,
                 player,
-                scale,
+                size,
+                opacity,
+                tint,
+                lit,
+                face_mode,
+                name,
+                owner,
             });
         }
     });
 
+    // ask the youtube pages for their real time instead of estimating,
+    // all at once so a slow page doesn't hold up our reply
+    let times = future::join_all(
+        youtube_browsers
+            .iter()
+            .map(|(_index, browser)| timeout(YOUTUBE_TIME_TIMEOUT, get_youtube_time(browser))),
+    )
+    .await;
+
+    for ((index, _browser), result) in youtube_browsers.iter().zip(times) {
+        match result {
+            Ok(Ok(time)) => {
+                if let Player::Youtube(ref mut yt) = &mut light_entities[*index].player {
+                    yt.time = time;
+                }
+            }
+
+            Ok(Err(e)) => {
+                debug!("get_youtube_time: {}", e);
+            }
+
+            Err(_) => {
+                debug!("get_youtube_time timed out");
+            }
+        }
+    }
+
     Message {
         entities: light_entities,
     }
 }
 
-pub async fn received_message(mut message: Message) -> Result<bool> {
+async fn get_youtube_time(browser: &RustRefBrowser) -> Result<Duration> {
+    let (time, state, duration) = future::try_join3(
+        YoutubePlayer::real_current_time(browser),
+        YoutubePlayer::player_state(browser),
+        YoutubePlayer::real_duration(browser),
+    )
+    .await?;
+
+    if state != PlayerState::Playing {
+        return Ok(time);
+    }
+
+    if duration > Duration::from_secs(0) && time > duration {
+        Ok(duration)
+    } else {
+        Ok(time)
+    }
+}
+
+pub async fn received_message(mut message: Message, sender: &str) -> Result<bool> {
     let mut had_data = false;
 
     for info in message.entities.drain(..) {
@@ -85,10 +230,71 @@ pub async fn received_message(mut message: Message) -> Result<bool> {
 
         debug!("creating {:#?}", info);
 
-        EntityManager::create_entity_from_light_entity(info).await?;
+        EntityManager::create_entity_from_light_entity(info, sender).await?;
 
         had_data = true;
     }
 
     Ok(had_data)
 }
+
+#[test]
+fn test_decode() {
+    // what an older version would send, None for released clients
+    fn encode_old<T: Serialize>(version: Option<u8>, entities: &[T]) -> String {
+        let mut data = Vec::new();
+        if let Some(version) = version {
+            data.extend_from_slice(MESSAGE_MAGIC);
+            data.push(version);
+        }
+        data.append(&mut bincode::serialize(entities).unwrap());
+
+        base64::encode(data)
+    }
+
+    let player = Player::from_input("https://www.youtube.com/watch?v=gQngg8iQipk").unwrap();
+
+    let message = Message {
+        entities: vec![LightEntity {
+            id: 1,
+            player: player.clone(),
+            pos: [1.0, 2.0, 3.0],
+            ang: [10.0, 20.0, 30.0],
+            size: [4.0, 3.0],
+            opacity: 0.5,
+            tint: [255, 0, 0],
+            lit: true,
+            face_mode: FaceMode::DoubleSidedMirrored,
+            name: Some("tv".to_string()),
+            owner: Some("SpiralP".to_string()),
+        }],
+    };
+    let decoded = decode(encode(&message).unwrap()).unwrap();
+    assert_eq!(decoded.entities[0].ang, [10.0, 20.0, 30.0]);
+    assert_eq!(decoded.entities[0].name.as_deref(), Some("tv"));
+    assert_eq!(decoded.entities[0].owner.as_deref(), Some("SpiralP"));
+    assert_eq!(decoded.entities[0].size, [4.0, 3.0]);
+    assert_eq!(decoded.entities[0].opacity, 0.5);
+    assert_eq!(decoded.entities[0].tint, [255, 0, 0]);
+    assert!(decoded.entities[0].lit);
+    assert_eq!(decoded.entities[0].face_mode, FaceMode::DoubleSidedMirrored);
+
+    let old = LightEntityV0 {
+        id: 1,
+        player,
+        pos: [1.0, 2.0, 3.0],
+        ang: [10.0, 20.0],
+        scale: 0.5,
+    };
+    let decoded = decode(encode_old(None, &[old])).unwrap();
+    assert_eq!(decoded.entities[0].pos, [1.0, 2.0, 3.0]);
+    assert_eq!(decoded.entities[0].ang, [10.0, 20.0, 0.0]);
+    assert_eq!(decoded.entities[0].size, [8.0, 4.5]);
+    assert_eq!(decoded.entities[0].opacity, 1.0);
+    assert_eq!(decoded.entities[0].tint, [255, 255, 255]);
+    assert!(!decoded.entities[0].lit);
+    assert_eq!(decoded.entities[0].face_mode, FaceMode::default());
+
+    let future: [LightEntityV0; 0] = [];
+    assert!(decode(encode_old(Some(MESSAGE_VERSION + 1), &future)).is_err());
+}
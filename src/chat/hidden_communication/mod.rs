@@ -4,9 +4,9 @@ mod whispers;
 
 pub use self::encoding::LightEntity;
 use super::SIMULATING;
-use crate::async_manager::AsyncManager;
+use crate::{async_manager::AsyncManager, host};
 use classicube_helpers::{detour::static_detour, CellGetSet};
-use classicube_sys::{Chat_AddOf, MsgType_MSG_TYPE_NORMAL, Server};
+use classicube_sys::{Chat_AddOf, MsgType_MSG_TYPE_NORMAL};
 use futures::channel::oneshot;
 use log::debug;
 use std::{
@@ -39,7 +39,7 @@ fn chat_add_hook(text: *const classicube_sys::String, message_type: c_int) {
 pub fn initialize() {
     debug!("initialize hidden_communication");
 
-    if unsafe { Server.IsSinglePlayer } != 0 {
+    if host::get().is_single_player() {
         return;
     }
 
@@ -52,7 +52,7 @@ pub fn initialize() {
 }
 
 pub fn on_new_map_loaded() {
-    if unsafe { Server.IsSinglePlayer } == 0 {
+    if !host::get().is_single_player() {
         clients::query();
     }
 }
@@ -80,7 +80,7 @@ async fn wait_for_message() -> String {
 }
 
 #[must_use]
-fn handle_chat_message(message: String) -> bool {
+pub(crate) fn handle_chat_message(message: String) -> bool {
     // don't recurse from Chat::send()
     if SIMULATING.get() {
         return false;
@@ -1,11 +1,7 @@
 use super::{encoding, is_incoming_whisper, is_outgoing_whisper, wait_for_message, SHOULD_BLOCK};
-use crate::{
-    async_manager::AsyncManager,
-    chat::{Chat, ENTITIES, TAB_LIST},
-    error::*,
-};
+use crate::{async_manager::AsyncManager, chat::Chat, error::*, host};
 use async_std::future::timeout;
-use classicube_helpers::{shared::FutureShared, CellGetSet, OptionWithInner};
+use classicube_helpers::{shared::FutureShared, CellGetSet};
 use log::{debug, info, warn};
 use std::time::Duration;
 
@@ -45,27 +41,12 @@ async fn handle_request(message: String) -> Result<()> {
     info!("from {:?}", nick_name);
 
     // find real nick
-    let maybe_real_name = TAB_LIST
-        .with_inner(|tab_list| {
-            tab_list
-                .find_entry_by_nick_name(&nick_name)
-                .and_then(|entry| {
-                    let id = entry.get_id();
-
-                    // make sure they're real
-                    ENTITIES
-                        .with_inner(|entities| {
-                            if entities.get(id).is_some() {
-                                Some(entry.get_real_name()?)
-                            } else {
-                                None
-                            }
-                        })
-                        .chain_err(|| "ENTITIES")
-                        .ok()?
-                })
-        })
-        .chain_err(|| "TAB_LIST")?;
+    let host = host::get();
+    let maybe_real_name = host
+        .find_tab_entry(&nick_name)
+        // make sure they're real
+        .filter(|entry| host.get_player(entry.id).is_some())
+        .map(|entry| entry.real_name);
 
     if let Some(real_name) = maybe_real_name {
         let mut mutex = SENDING.with(|m| m.clone());
@@ -85,7 +66,7 @@ async fn handle_request(message: String) -> Result<()> {
 async fn send_reply(real_name: String) -> Result<()> {
     debug!("sending to {:?}", real_name);
 
-    let message = encoding::create_message();
+    let message = encoding::create_message().await;
     let encoded = encoding::encode(&message)?;
     Chat::send(format!("@{}+ !CEF!{}", real_name, encoded));
 
@@ -2,12 +2,11 @@ mod incoming;
 mod outgoing;
 
 use super::{encoding, wait_for_message, SHOULD_BLOCK};
-use crate::{async_manager::AsyncManager, chat::ENTITIES, error::*};
-use classicube_helpers::OptionWithInner;
+use crate::{async_manager::AsyncManager, error::*, host, metrics};
 use futures::{future::RemoteHandle, prelude::*};
 use log::{debug, warn};
 use rand::seq::SliceRandom;
-use std::cell::Cell;
+use std::{cell::Cell, time::Instant};
 
 thread_local!(
     static LISTENER: Cell<Option<RemoteHandle<()>>> = Default::default();
@@ -24,16 +23,18 @@ pub async fn start_whispering(players: Vec<(u8, String)>) -> Result<()> {
         .iter()
         .filter(|(id, _real_name)| {
             // check if they're on our map
-            ENTITIES
-                .with_inner(|entities| entities.get(*id).is_some())
-                .unwrap()
+            host::get().get_player(*id).is_some()
         })
         .collect();
 
     real_players.shuffle(&mut rand::thread_rng());
 
     for (_id, real_name) in real_players {
-        match outgoing::query_whisper(&real_name).await {
+        let before = Instant::now();
+        let result = outgoing::query_whisper(&real_name).await;
+        metrics::record_whisper(before.elapsed(), result.is_ok());
+
+        match result {
             Ok(had_data) => {
                 if had_data {
                     break;
@@ -77,3 +78,63 @@ fn is_incoming_whisper(message: &str) -> bool {
     message.len() >= 6
         && (&message.as_bytes()[0..1] == b"&" && &message.as_bytes()[2..6] == b"[>] ")
 }
+
+#[test]
+fn test_whispers() {
+    let origin = classicube_sys::Vec3 {
+        X: 0.0,
+        Y: 0.0,
+        Z: 0.0,
+    };
+
+    let host = crate::host::FakeHost::install("SpiralP");
+    host.add_player(2, "&uGoodly", None, origin);
+    host.add_player(3, "&uAway", None, origin);
+    host.remove_entity(3);
+
+    AsyncManager::initialize_executors();
+    start_listening();
+
+    // asking Goodly for their screens
+    let (f, remote_handle) =
+        start_whispering(vec![(3, "Away".to_string()), (2, "Goodly".to_string())]).remote_handle();
+    AsyncManager::spawn_local_on_main_thread(f);
+    AsyncManager::step();
+
+    // Away isn't on our map
+    assert_eq!(host.take_sent(), vec!["@Goodly+ ?CEF?"]);
+
+    let encoded = encoding::encode(&encoding::Message {
+        entities: Vec::new(),
+    })
+    .unwrap();
+    let (first, rest) = encoded.split_at(encoded.len() / 2);
+
+    assert!(host.receive_chat("&7[<] &uGoodly: &f?CEF?"));
+    assert!(host.receive_chat(&format!("&9[>] &uGoodly: &f!CEF!{}", first)));
+    assert!(host.receive_chat(&format!("> &f{}", rest)));
+    // the reply is over, everything else shows
+    assert!(!host.receive_chat("&eGoodly is AFK"));
+
+    remote_handle
+        .now_or_never()
+        .expect("whispering didn't finish")
+        .unwrap();
+    assert!(host.take_sent().is_empty());
+
+    // Goodly asking us
+    assert!(host.receive_chat("&9[>] &uGoodly: &f?CEF?"));
+
+    let sent = host.take_sent();
+    assert_eq!(sent.len(), 1);
+    let reply = sent[0].trim_start_matches("@Goodly+ !CEF!");
+    assert_ne!(reply, sent[0]);
+    assert!(encoding::decode(reply).unwrap().entities.is_empty());
+
+    assert!(host.receive_chat(&format!("&7[<] &uGoodly: &f!CEF!{}", first)));
+    assert!(host.receive_chat(&format!("> &f{}", rest)));
+    assert!(!host.receive_chat("&eGoodly is AFK"));
+
+    stop_listening();
+    AsyncManager::shutdown_executors();
+}
@@ -72,5 +72,5 @@ pub async fn query_whisper(real_name: &str) -> Result<bool> {
 
     let message = encoding::decode(full_message_encoded)?;
     debug!("decoded {:#?}", message);
-    Ok(encoding::received_message(message).await?)
+    Ok(encoding::received_message(message, real_name).await?)
 }
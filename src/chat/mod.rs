@@ -1,17 +1,22 @@
 mod chat_command;
+mod commands;
+mod completion;
 pub mod hidden_communication;
+pub mod permissions;
+mod rate_limit;
 
 pub use self::chat_command::{command_callback, CefChatCommand};
-use crate::async_manager::AsyncManager;
+use crate::{
+    async_manager::AsyncManager,
+    host::{self, PlayerEntity},
+};
 use classicube_helpers::{
-    entities::{Entities, ENTITY_SELF_ID},
+    entities::ENTITY_SELF_ID,
     events::chat::{ChatReceivedEvent, ChatReceivedEventHandler},
-    tab_list::{remove_color, TabList},
+    tab_list::remove_color,
     CellGetSet,
 };
-use classicube_sys::{
-    Chat_Add, Chat_Send, MsgType, MsgType_MSG_TYPE_NORMAL, OwnedString, Server, Vec3,
-};
+use classicube_sys::{MsgType, MsgType_MSG_TYPE_NORMAL, Vec3};
 use deunicode::deunicode;
 use futures::{future::RemoteHandle, prelude::*};
 use log::{debug, info};
@@ -28,13 +33,10 @@ thread_local!(
     static SIMULATING: Cell<bool> = Cell::new(false);
 );
 
+// if the last command line got past the rate limit,
+// "> &f" continuations of it are the same command
 thread_local!(
-    static TAB_LIST: RefCell<Option<TabList>> = RefCell::new(None);
-);
-
-// TODO make this not public :p
-thread_local!(
-    pub static ENTITIES: RefCell<Option<Entities>> = RefCell::new(None);
+    static LAST_RATE_LIMIT: Cell<Option<bool>> = Cell::new(None);
 );
 
 thread_local!(
@@ -68,17 +70,8 @@ impl Chat {
             },
         );
 
-        TAB_LIST.with(|cell| {
-            let tab_list = &mut *cell.borrow_mut();
-            *tab_list = Some(TabList::new());
-        });
-
-        ENTITIES.with(|cell| {
-            let tab_list = &mut *cell.borrow_mut();
-            *tab_list = Some(Entities::new());
-        });
-
         hidden_communication::initialize();
+        permissions::initialize();
     }
 
     pub fn on_new_map_loaded(&mut self) {
@@ -87,7 +80,7 @@ impl Chat {
         hidden_communication::on_new_map_loaded();
 
         #[cfg(debug_assertions)]
-        if unsafe { Server.IsSinglePlayer } != 0 {
+        if host::get().is_single_player() {
             AsyncManager::spawn_local_on_main_thread(async {
                 AsyncManager::sleep(Duration::from_millis(300)).await;
 
@@ -99,16 +92,6 @@ impl Chat {
     pub fn shutdown(&mut self) {
         hidden_communication::shutdown();
 
-        ENTITIES.with(|cell| {
-            let entities = &mut *cell.borrow_mut();
-            entities.take();
-        });
-
-        TAB_LIST.with(|cell| {
-            let tab_list = &mut *cell.borrow_mut();
-            tab_list.take();
-        });
-
         self.chat_command.shutdown();
     }
 
@@ -121,12 +104,8 @@ impl Chat {
             s.truncate(255);
         }
 
-        let owned_string = OwnedString::new(s);
-
         SIMULATING.set(true);
-        unsafe {
-            Chat_Add(owned_string.as_cc_string());
-        }
+        host::get().add_chat(&s);
         SIMULATING.set(false);
     }
 
@@ -135,11 +114,7 @@ impl Chat {
         info!("{}", s);
         let s = deunicode(&s);
 
-        let owned_string = OwnedString::new(s);
-
-        unsafe {
-            Chat_Send(owned_string.as_cc_string(), 0);
-        }
+        host::get().send_chat(&s);
     }
 }
 
@@ -151,7 +126,7 @@ fn test_unicode() {
     assert_eq!(deunicode(input), "Luigi, brother...[hitupuhotupu MIX]");
 }
 
-fn handle_chat_received(message: String, message_type: MsgType) {
+pub(crate) fn handle_chat_received(message: String, message_type: MsgType) {
     if SIMULATING.get() {
         return;
     }
@@ -159,75 +134,106 @@ fn handle_chat_received(message: String, message_type: MsgType) {
         return;
     }
 
-    if let Some((id, _name, message)) = find_player_from_message(message.clone()) {
-        // let name: String = remove_color(name).trim().to_string();
-
-        // don't remove colors because & might be part of url!
-        // let message: String = remove_color(message).trim().to_string();
-
-        let mut split = message
-            .split(' ')
-            .map(|a| a.to_string())
-            .collect::<Vec<String>>();
-
-        if split
-            .get(0)
-            .map(|first| remove_color(first).trim() == "cef")
-            .unwrap_or(false)
-        {
-            // remove "cef"
-            split.remove(0);
-
-            let player_snapshot = ENTITIES.with(|cell| {
-                let entities = &*cell.borrow();
-                let entities = entities.as_ref().unwrap();
-                entities.get(id).map(|entity| {
-                    let position = entity.get_position();
-                    let eye_position = entity.get_eye_position();
-                    let head = entity.get_head();
-                    let rot = entity.get_rot();
-                    PlayerSnapshot {
-                        Position: position,
-                        eye_position,
-                        Pitch: head[0],
-                        Yaw: head[1],
-                        RotX: rot[0],
-                        RotY: rot[1],
-                        RotZ: rot[2],
+    if let Some((id, player_snapshot, split)) = parse_chat_command(message) {
+        FUTURE_HANDLE.with(|cell| {
+            let (remote, remote_handle) = async move {
+                if !host::get().is_single_player() {
+                    AsyncManager::sleep(Duration::from_millis(256)).await;
+                }
+
+                let is_self = id == ENTITY_SELF_ID;
+
+                if let Err(e) = command_callback(&player_snapshot, split, is_self).await {
+                    if is_self {
+                        Chat::print(format!("cef command error: {}", e));
                     }
-                })
-            });
+                }
+            }
+            .remote_handle();
 
-            if let Some(player_snapshot) = player_snapshot {
-                FUTURE_HANDLE.with(|cell| {
-                    let (remote, remote_handle) = async move {
-                        if unsafe { Server.IsSinglePlayer } == 0 {
-                            AsyncManager::sleep(Duration::from_millis(256)).await;
-                        }
+            cell.set(Some(remote_handle));
 
-                        let is_self = id == ENTITY_SELF_ID;
+            AsyncManager::spawn_local_on_main_thread(remote);
+        });
+    }
+}
 
-                        if let Err(e) = command_callback(&player_snapshot, split, is_self).await {
-                            if is_self {
-                                Chat::print(format!("cef command error: {}", e));
-                            }
-                        }
-                    }
-                    .remote_handle();
+/// entity id, who said it, and the args after "cef"
+///
+/// None if it isn't a cef command or we shouldn't run it.
+fn parse_chat_command(message: String) -> Option<(u8, PlayerSnapshot, Vec<String>)> {
+    let is_continuation = message.starts_with("> &f");
+    if !is_continuation {
+        LAST_RATE_LIMIT.set(None);
+    }
+
+    let (id, name, group, message) = match find_player_from_message(message.clone()) {
+        Some(found) => found,
+        None => {
+            if message.contains(": ") && !message.starts_with("&5Discord: &f[") {
+                log::warn!("couldn't match player for {:?}", message);
+            }
+            return None;
+        }
+    };
+
+    // let name: String = remove_color(name).trim().to_string();
 
-                    cell.set(Some(remote_handle));
+    // don't remove colors because & might be part of url!
+    // let message: String = remove_color(message).trim().to_string();
 
-                    AsyncManager::spawn_local_on_main_thread(remote);
-                });
+    let mut split = message
+        .split(' ')
+        .map(|a| a.to_string())
+        .collect::<Vec<String>>();
+
+    if !split
+        .get(0)
+        .map(|first| remove_color(first).trim() == "cef")
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    // remove "cef"
+    split.remove(0);
+
+    let is_op = group
+        .as_ref()
+        .map(|group| permissions::is_op_group(group))
+        .unwrap_or(false);
+
+    if id != ENTITY_SELF_ID {
+        if permissions::is_blocked(&name) {
+            debug!("ignoring cef command from {:?}", name);
+            return None;
+        }
+
+        // friends and ops get what they're allowed to do
+        if !is_op && !permissions::is_friend(&name) {
+            let allowed = match LAST_RATE_LIMIT.get() {
+                Some(allowed) if is_continuation => allowed,
+                _ => rate_limit::check(&name),
+            };
+            LAST_RATE_LIMIT.set(Some(allowed));
+
+            if !allowed {
+                return None;
             }
         }
-    } else if message.contains(": ") && !message.starts_with("&5Discord: &f[") {
-        log::warn!("couldn't match player for {:?}", message);
     }
+
+    let player = host::get().get_player(id)?;
+
+    Some((id, PlayerSnapshot::new(name, is_op, &player), split))
 }
 
 #[allow(non_snake_case)]
 pub struct PlayerSnapshot {
+    /// real name without colors, our username for our own commands
+    pub name: String,
+    /// in one of the server's op groups in the tab list
+    pub is_op: bool,
     pub Position: Vec3,
     pub eye_position: Vec3,
     pub Pitch: f32,
@@ -237,11 +243,28 @@ pub struct PlayerSnapshot {
     pub RotZ: f32,
 }
 
-fn find_player_from_message(mut full_msg: String) -> Option<(u8, String, String)> {
-    if unsafe { Server.IsSinglePlayer } != 0 {
-        // in singleplayer there is no tab list, even self id infos are null
+impl PlayerSnapshot {
+    pub fn new(name: String, is_op: bool, player: &PlayerEntity) -> Self {
+        Self {
+            name,
+            is_op,
+            Position: player.position,
+            eye_position: player.eye_position,
+            Pitch: player.head[0],
+            Yaw: player.head[1],
+            RotX: player.rot[0],
+            RotY: player.rot[1],
+            RotZ: player.rot[2],
+        }
+    }
+}
 
-        return Some((ENTITY_SELF_ID, String::new(), full_msg));
+/// entity id, real name, tab list group, message
+fn find_player_from_message(mut full_msg: String) -> Option<(u8, String, Option<String>, String)> {
+    if host::get().is_single_player() {
+        // in singleplayer there is no tab list, even self id infos are null
+        // so use our username like /client commands do, owners depend on it
+        return Some((ENTITY_SELF_ID, host::get().get_username(), None, full_msg));
     }
 
     LAST_CHAT.with(|cell| {
@@ -283,16 +306,109 @@ fn find_player_from_message(mut full_msg: String) -> Option<(u8, String, String)
             let said_text = right.to_string();
 
             // lookup entity id from nick_name by using TabList
-            TAB_LIST.with(|cell| {
-                let tab_list = &*cell.borrow();
-                tab_list
-                    .as_ref()
-                    .unwrap()
-                    .find_entry_by_nick_name(&full_nick)
-                    .map(|entry| (entry.get_id(), entry.get_real_name().unwrap(), said_text))
-            })
+            host::get()
+                .find_tab_entry(&full_nick)
+                .map(|entry| (entry.id, entry.real_name, entry.group, said_text))
         } else {
             None
         }
     })
 }
+
+#[test]
+fn test_print() {
+    let host = host::FakeHost::install("SpiralP");
+
+    Chat::print("ｈｉ");
+    Chat::print("a".repeat(300));
+    Chat::send("/clients");
+
+    let added = host.take_added();
+    assert_eq!(added[0], "hi");
+    assert_eq!(added[1].len(), 255);
+    assert_eq!(host.take_sent(), vec!["/clients"]);
+}
+
+#[test]
+fn test_parse_chat_command() {
+    let origin = Vec3 {
+        X: 0.0,
+        Y: 0.0,
+        Z: 0.0,
+    };
+
+    let host = host::FakeHost::install("SpiralP");
+    host.add_player(ENTITY_SELF_ID, "&fSpiralP", None, origin);
+    host.add_player(
+        2,
+        "&aGoodly",
+        Some("&cOps"),
+        Vec3 {
+            X: 1.0,
+            Y: 2.0,
+            Z: 3.0,
+        },
+    );
+    host.add_player(3, "&7Guest", Some("Guests"), origin);
+
+    let (id, player, args) =
+        parse_chat_command("&aGoodly: cef create https://example.com/?a&b".into()).unwrap();
+    assert_eq!(id, 2);
+    assert_eq!(player.name, "Goodly");
+    assert!(player.is_op);
+    assert_eq!(player.Position.Y, 2.0);
+    // & might be part of a url so colors stay
+    assert_eq!(args, vec!["create", "https://example.com/?a&b"]);
+
+    let (id, player, args) = parse_chat_command("&fSpiralP: &fcef close".into()).unwrap();
+    assert_eq!(id, ENTITY_SELF_ID);
+    assert_eq!(player.name, "SpiralP");
+    assert_eq!(args, vec!["close"]);
+
+    // long messages are continued on the next line
+    parse_chat_command("&7Guest: cef create".into()).unwrap();
+    let (id, player, args) = parse_chat_command("> &fhttps://example.com".into()).unwrap();
+    assert_eq!(id, 3);
+    assert!(!player.is_op);
+    assert_eq!(args, vec!["create", "https://example.com"]);
+
+    assert!(parse_chat_command("&aGoodly: hello".into()).is_none());
+    assert!(parse_chat_command("&eGoodly joined the game".into()).is_none());
+    // not in the tab list
+    assert!(parse_chat_command("&aNobody: cef create".into()).is_none());
+    // in the tab list but not on our map
+    host.remove_entity(3);
+    assert!(parse_chat_command("&7Guest: cef close".into()).is_none());
+
+    // there's no tab list in singleplayer, everything is from us
+    host.set_single_player(true);
+    let (id, player, args) = parse_chat_command("cef play".into()).unwrap();
+    assert_eq!(id, ENTITY_SELF_ID);
+    assert_eq!(player.name, "SpiralP");
+    assert_eq!(args, vec!["play"]);
+}
+
+#[test]
+fn test_rate_limit_continuation() {
+    let host = host::FakeHost::install("SpiralP");
+    host.add_player(
+        3,
+        "&7Guest",
+        Some("Guests"),
+        Vec3 {
+            X: 0.0,
+            Y: 0.0,
+            Z: 0.0,
+        },
+    );
+
+    // a wrapped command only counts once, so all of these fit the limit
+    for _ in 0..5 {
+        assert!(parse_chat_command("&7Guest: cef play https://example.com/".into()).is_some());
+        assert!(parse_chat_command("> &fa/very/long/path".into()).is_some());
+    }
+
+    // and a continuation of a dropped command is dropped too
+    assert!(parse_chat_command("&7Guest: cef play https://example.com/".into()).is_none());
+    assert!(parse_chat_command("> &fa/very/long/path".into()).is_none());
+}
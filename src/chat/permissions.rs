@@ -0,0 +1,295 @@
+use super::Chat;
+use crate::error::*;
+use classicube_helpers::tab_list::remove_color;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, fs, io::ErrorKind as IoErrorKind};
+
+// stored next to cef.log
+const SETTINGS_PATH: &str = "cef-permissions.json";
+
+// tab list groups that count as server ops
+const OP_GROUPS: &[&str] = &[
+    "op",
+    "operator",
+    "admin",
+    "mod",
+    "moderator",
+    "owner",
+    "staff",
+];
+
+thread_local!(
+    static SETTINGS: RefCell<Settings> = RefCell::new(Settings::default());
+);
+
+/// Who may do something to a screen we can see
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Access {
+    /// anyone who isn't blocked
+    Anyone,
+    /// the screen's owner, friends and ops
+    Owner,
+    /// friends and ops
+    Friends,
+    /// only us
+    Me,
+}
+
+impl Access {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "anyone" | "everyone" | "all" => Access::Anyone,
+            "owner" | "owners" => Access::Owner,
+            "friends" | "friend" => Access::Friends,
+            "me" | "self" | "nobody" => Access::Me,
+            _ => bail!("expected anyone, owner, friends or me"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// creating screens or changing what they show
+    Play,
+    /// changing where and how screens are drawn
+    Move,
+    /// removing screens
+    Close,
+}
+
+impl Action {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "play" => Action::Play,
+            "move" => Action::Move,
+            "close" => Action::Close,
+            _ => bail!("expected play, move or close"),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    friends: Vec<String>,
+    blocked: Vec<String>,
+    play: Access,
+    #[serde(rename = "move")]
+    move_: Access,
+    close: Access,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            friends: Vec::new(),
+            blocked: Vec::new(),
+            // anyone could do everything before there were permissions
+            play: Access::Anyone,
+            move_: Access::Anyone,
+            close: Access::Anyone,
+        }
+    }
+}
+
+impl Settings {
+    fn get_access(&self, action: Action) -> Access {
+        match action {
+            Action::Play => self.play,
+            Action::Move => self.move_,
+            Action::Close => self.close,
+        }
+    }
+
+    fn set_access(&mut self, action: Action, access: Access) {
+        match action {
+            Action::Play => self.play = access,
+            Action::Move => self.move_ = access,
+            Action::Close => self.close = access,
+        }
+    }
+
+    fn is_allowed(&self, name: &str, is_op: bool, action: Action, owner: Option<&str>) -> bool {
+        if contains_name(&self.blocked, name) {
+            return false;
+        }
+
+        let is_friend = is_op || contains_name(&self.friends, name);
+        let is_owner = owner.map(|owner| same_name(owner, name)).unwrap_or(false);
+
+        match self.get_access(action) {
+            Access::Anyone => true,
+            Access::Owner => is_owner || is_friend,
+            Access::Friends => is_friend,
+            Access::Me => false,
+        }
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    remove_color(a)
+        .trim()
+        .eq_ignore_ascii_case(remove_color(b).trim())
+}
+
+fn contains_name(names: &[String], name: &str) -> bool {
+    names.iter().any(|n| same_name(n, name))
+}
+
+/// if a tab list group name is one of the server's ops
+pub fn is_op_group(group: &str) -> bool {
+    let group = remove_color(group).trim().to_lowercase();
+    let group = group.trim_end_matches('s');
+
+    OP_GROUPS.iter().any(|&op| group == op)
+}
+
+pub fn initialize() {
+    match load() {
+        Ok(settings) => {
+            SETTINGS.with(|cell| *cell.borrow_mut() = settings);
+        }
+
+        Err(e) => {
+            warn!("couldn't load {}: {}", SETTINGS_PATH, e);
+        }
+    }
+}
+
+fn load() -> Result<Settings> {
+    match fs::read_to_string(SETTINGS_PATH) {
+        Ok(data) => Ok(serde_json::from_str(&data)?),
+        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save() {
+    let result = SETTINGS.with(|cell| {
+        let settings = &*cell.borrow();
+        let data = serde_json::to_string_pretty(settings)?;
+        fs::write(SETTINGS_PATH, data)?;
+
+        Ok::<_, Error>(())
+    });
+
+    if let Err(e) = result {
+        Chat::print(format!("couldn't save {}: {}", SETTINGS_PATH, e));
+    }
+}
+
+/// if `name` may run a command that needs `action` on a screen owned by `owner`
+///
+/// Our own commands are checked by the caller, this is for other players.
+pub fn is_allowed(name: &str, is_op: bool, action: Action, owner: Option<&str>) -> bool {
+    SETTINGS.with(|cell| cell.borrow().is_allowed(name, is_op, action, owner))
+}
+
+pub fn set_access(action: Action, access: Access) {
+    SETTINGS.with(|cell| cell.borrow_mut().set_access(action, access));
+    save();
+}
+
+pub fn add_friend(name: &str) {
+    let changed = SETTINGS.with(|cell| add_name(&mut cell.borrow_mut().friends, name));
+    if changed {
+        save();
+    }
+}
+
+pub fn remove_friend(name: &str) {
+    let changed = SETTINGS.with(|cell| remove_name(&mut cell.borrow_mut().friends, name));
+    if changed {
+        save();
+    }
+}
+
+pub fn block(name: &str) {
+    let changed = SETTINGS.with(|cell| add_name(&mut cell.borrow_mut().blocked, name));
+    if changed {
+        save();
+    }
+}
+
+pub fn unblock(name: &str) {
+    let changed = SETTINGS.with(|cell| remove_name(&mut cell.borrow_mut().blocked, name));
+    if changed {
+        save();
+    }
+}
+
+pub fn is_friend(name: &str) -> bool {
+    SETTINGS.with(|cell| contains_name(&cell.borrow().friends, name))
+}
+
+pub fn is_blocked(name: &str) -> bool {
+    SETTINGS.with(|cell| contains_name(&cell.borrow().blocked, name))
+}
+
+fn add_name(names: &mut Vec<String>, name: &str) -> bool {
+    if contains_name(names, name) {
+        false
+    } else {
+        debug!("adding {:?}", name);
+        names.push(remove_color(name).trim().to_string());
+        true
+    }
+}
+
+fn remove_name(names: &mut Vec<String>, name: &str) -> bool {
+    let len = names.len();
+    names.retain(|n| !same_name(n, name));
+    names.len() != len
+}
+
+/// lines describing our current settings
+pub fn describe() -> Vec<String> {
+    SETTINGS.with(|cell| {
+        let settings = &*cell.borrow();
+
+        vec![
+            format!(
+                "play: {:?}, move: {:?}, close: {:?}",
+                settings.play, settings.move_, settings.close
+            ),
+            format!("friends: {}", settings.friends.join(", ")),
+            format!("ignored: {}", settings.blocked.join(", ")),
+        ]
+    })
+}
+
+#[test]
+fn test_is_allowed() {
+    let mut settings = Settings::default();
+    settings.friends.push("Friend".to_string());
+    settings.blocked.push("Griefer".to_string());
+
+    // anyone can play by default, unless blocked
+    assert!(settings.is_allowed("Stranger", false, Action::Play, None));
+    assert!(!settings.is_allowed("Griefer", false, Action::Play, None));
+    assert!(!settings.is_allowed("griefer", true, Action::Play, None));
+
+    // anyone can move and close by default too
+    assert!(settings.is_allowed("Stranger", false, Action::Move, Some("Owner")));
+    assert!(settings.is_allowed("Stranger", false, Action::Close, None));
+
+    // only owners, friends and ops can move
+    settings.set_access(Action::Move, Access::Owner);
+    assert!(!settings.is_allowed("Stranger", false, Action::Move, Some("Owner")));
+    assert!(settings.is_allowed("&aowner", false, Action::Move, Some("Owner")));
+    assert!(settings.is_allowed("friend", false, Action::Move, Some("Owner")));
+    assert!(settings.is_allowed("Op", true, Action::Close, None));
+
+    settings.set_access(Action::Close, Access::Me);
+    assert!(!settings.is_allowed("Friend", false, Action::Close, None));
+}
+
+#[test]
+fn test_is_op_group() {
+    assert!(is_op_group("&cOps"));
+    assert!(is_op_group("Admins"));
+    assert!(is_op_group("&9Owner"));
+    assert!(!is_op_group("Guest"));
+    assert!(!is_op_group("Builders"));
+}
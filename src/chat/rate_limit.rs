@@ -0,0 +1,154 @@
+use super::Chat;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+// each player can run MAX_COMMANDS commands every WINDOW
+const WINDOW: Duration = Duration::from_secs(10);
+const MAX_COMMANDS: usize = 5;
+
+// dropping this many commands in a WINDOW ignores the player for IGNORE_DURATION
+const AUTO_IGNORE_DROPPED: usize = 10;
+const IGNORE_DURATION: Duration = Duration::from_secs(5 * 60);
+
+thread_local!(
+    static RATE_LIMITER: RefCell<RateLimiter> = RefCell::new(RateLimiter::default());
+);
+
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Allow,
+    Drop,
+    AutoIgnore,
+}
+
+#[derive(Default)]
+struct History {
+    commands: VecDeque<Instant>,
+    dropped: VecDeque<Instant>,
+    ignored_until: Option<Instant>,
+}
+
+impl History {
+    fn expire(&mut self, now: Instant) {
+        let expired = |time: &Instant| now.saturating_duration_since(*time) > WINDOW;
+        while self.commands.front().map(expired).unwrap_or(false) {
+            self.commands.pop_front();
+        }
+        while self.dropped.front().map(expired).unwrap_or(false) {
+            self.dropped.pop_front();
+        }
+
+        if self
+            .ignored_until
+            .map(|until| now >= until)
+            .unwrap_or(false)
+        {
+            self.ignored_until = None;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.commands.is_empty() && self.dropped.is_empty() && self.ignored_until.is_none()
+    }
+}
+
+#[derive(Default)]
+struct RateLimiter {
+    // lowercase name, history
+    players: HashMap<String, History>,
+}
+
+impl RateLimiter {
+    fn check(&mut self, name: &str, now: Instant) -> Verdict {
+        // forget players who stopped sending commands
+        self.players.retain(|_, history| {
+            history.expire(now);
+            !history.is_empty()
+        });
+
+        let history = self.players.entry(name.to_lowercase()).or_default();
+
+        if history.ignored_until.is_some() {
+            return Verdict::Drop;
+        }
+
+        if history.commands.len() < MAX_COMMANDS {
+            history.commands.push_back(now);
+            return Verdict::Allow;
+        }
+
+        history.dropped.push_back(now);
+        if history.dropped.len() >= AUTO_IGNORE_DROPPED {
+            history.dropped.clear();
+            history.ignored_until = Some(now + IGNORE_DURATION);
+            Verdict::AutoIgnore
+        } else {
+            Verdict::Drop
+        }
+    }
+}
+
+/// if a command from `name` should run
+///
+/// Players who keep spamming get ignored for a while.
+pub fn check(name: &str) -> bool {
+    let verdict = RATE_LIMITER.with(|cell| cell.borrow_mut().check(name, Instant::now()));
+
+    match verdict {
+        Verdict::Allow => true,
+
+        Verdict::Drop => {
+            log::warn!("rate limiting cef command from {:?}", name);
+            false
+        }
+
+        Verdict::AutoIgnore => {
+            Chat::print(format!(
+                "cef: ignoring {} for {} minutes for spamming commands",
+                name,
+                IGNORE_DURATION.as_secs() / 60
+            ));
+            false
+        }
+    }
+}
+
+#[test]
+fn test_rate_limiter() {
+    let mut rate_limiter = RateLimiter::default();
+    let start = Instant::now();
+
+    for _ in 0..MAX_COMMANDS {
+        assert_eq!(rate_limiter.check("Griefer", start), Verdict::Allow);
+    }
+    assert_eq!(rate_limiter.check("griefer", start), Verdict::Drop);
+
+    // other players aren't affected
+    assert_eq!(rate_limiter.check("Friend", start), Verdict::Allow);
+
+    // allowed again after the window passes
+    let later = start + WINDOW + Duration::from_secs(1);
+    assert_eq!(rate_limiter.check("Griefer", later), Verdict::Allow);
+
+    let mut verdict = Verdict::Allow;
+    for _ in 0..(MAX_COMMANDS + AUTO_IGNORE_DROPPED) {
+        verdict = rate_limiter.check("Griefer", later);
+        if verdict == Verdict::AutoIgnore {
+            break;
+        }
+    }
+    assert_eq!(verdict, Verdict::AutoIgnore);
+
+    // ignored even after the window passes, until it runs out
+    let later = later + WINDOW + Duration::from_secs(1);
+    assert_eq!(rate_limiter.check("Griefer", later), Verdict::Drop);
+    let later = later + IGNORE_DURATION;
+    assert_eq!(rate_limiter.check("Griefer", later), Verdict::Allow);
+
+    // quiet players are forgotten
+    assert!(!rate_limiter.players.contains_key("friend"));
+    assert_eq!(rate_limiter.players.len(), 1);
+}
@@ -0,0 +1,203 @@
+use super::EntityManager;
+use crate::{async_manager::AsyncManager, host, players::PlayerTrait};
+use classicube_helpers::CellGetSet;
+use classicube_sys::{Vec3, ENTITIES_SELF_ID};
+use futures::{future::RemoteHandle, prelude::*};
+use log::debug;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    time::Duration,
+};
+
+// same as the game's tick rate
+const STEP_RATE: Duration = Duration::from_millis(50);
+
+// volume is 0 at this many blocks away
+const MAX_DISTANCE: f32 = 30.0;
+
+// don't send a new volume unless it changed by more than this
+const VOLUME_THRESHOLD: f32 = 0.01;
+
+// ignore tiny movements
+const MOVE_THRESHOLD_SQUARED: f32 = 0.01 * 0.01;
+
+thread_local!(
+    static GLOBAL_VOLUME: Cell<f32> = Cell::new(1.0);
+);
+
+thread_local!(
+    static SETTINGS_CHANGED: Cell<bool> = Cell::new(false);
+);
+
+thread_local!(
+    static LAST_LISTENER_POSITION: Cell<Option<Vec3>> = Cell::new(None);
+);
+
+// entity_id, last computed state
+thread_local!(
+    static SCREENS: RefCell<HashMap<usize, ScreenAudio>> = RefCell::new(HashMap::new());
+);
+
+struct ScreenAudio {
+    position: Vec3,
+
+    /// last volume the page accepted
+    volume: Option<f32>,
+}
+
+/// Recomputes every screen's volume in one pass, only when the local player
+/// or a screen moves, or the settings change.
+pub struct AudioScheduler {
+    step_loop_handle: Option<RemoteHandle<()>>,
+}
+
+impl AudioScheduler {
+    pub fn new() -> Self {
+        Self {
+            step_loop_handle: None,
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        debug!("initialize audio_scheduler");
+
+        let (f, remote_handle) = async {
+            loop {
+                AsyncManager::sleep(STEP_RATE).await;
+                Self::step();
+            }
+        }
+        .remote_handle();
+
+        AsyncManager::spawn_local_on_main_thread(f);
+        self.step_loop_handle = Some(remote_handle);
+    }
+
+    pub fn shutdown(&mut self) {
+        self.step_loop_handle.take();
+
+        SCREENS.with(|cell| {
+            let screens = &mut *cell.borrow_mut();
+            screens.clear();
+        });
+        LAST_LISTENER_POSITION.set(None);
+    }
+
+    /// volume is a float between 0-1
+    pub fn set_global_volume(volume: f32) {
+        GLOBAL_VOLUME.set(volume.max(0.0).min(1.0));
+        SETTINGS_CHANGED.set(true);
+    }
+
+    pub fn get_global_volume() -> f32 {
+        GLOBAL_VOLUME.get()
+    }
+
+    /// Forget what we sent to this screen, the page was (re)loaded
+    /// and has its start volume again
+    pub fn reset(entity_id: usize) {
+        SCREENS.with(|cell| {
+            let screens = &mut *cell.borrow_mut();
+            screens.remove(&entity_id);
+        });
+    }
+
+    fn step() {
+        let maybe_listener_position = host::get()
+            .get_player(ENTITIES_SELF_ID as _)
+            .map(|me| me.position);
+
+        let listener_position = match maybe_listener_position {
+            Some(position) => position,
+            None => return,
+        };
+
+        let listener_moved = match LAST_LISTENER_POSITION.get() {
+            Some(last) => has_moved(last, listener_position),
+            None => true,
+        };
+        if listener_moved {
+            LAST_LISTENER_POSITION.set(Some(listener_position));
+        }
+
+        let settings_changed = SETTINGS_CHANGED.get();
+        SETTINGS_CHANGED.set(false);
+
+        let global_volume = GLOBAL_VOLUME.get();
+
+        EntityManager::with_all_entities(|entities| {
+            SCREENS.with(|cell| {
+                let screens = &mut *cell.borrow_mut();
+
+                // forget removed entities
+                screens.retain(|entity_id, _| entities.contains_key(entity_id));
+
+                for (&entity_id, entity) in entities.iter_mut() {
+                    let position = entity.entity.Position;
+
+                    let screen = screens.entry(entity_id).or_insert(ScreenAudio {
+                        position,
+                        volume: None,
+                    });
+
+                    let screen_moved = has_moved(screen.position, position);
+                    screen.position = position;
+
+                    if !listener_moved
+                        && !screen_moved
+                        && !settings_changed
+                        && screen.volume.is_some()
+                    {
+                        continue;
+                    }
+
+                    let volume = global_volume * distance_volume(listener_position, position);
+
+                    if let Some(last_volume) = screen.volume {
+                        if !should_send(last_volume, volume) {
+                            continue;
+                        }
+                    }
+
+                    let mut browser = match entity.browser.as_ref() {
+                        Some(browser) => browser.clone(),
+                        None => continue,
+                    };
+
+                    // fails if the page isn't loaded yet, try again next tick
+                    if entity.player.set_volume(&mut browser, volume).is_ok() {
+                        screen.volume = Some(volume);
+                    }
+                }
+            });
+        });
+    }
+}
+
+fn has_moved(last: Vec3, current: Vec3) -> bool {
+    (current - last).length_squared() > MOVE_THRESHOLD_SQUARED
+}
+
+fn distance_volume(listener_position: Vec3, screen_position: Vec3) -> f32 {
+    let percent = (screen_position - listener_position)
+        .length_squared()
+        .sqrt()
+        / MAX_DISTANCE;
+    (1.0 - percent).max(0.0).min(1.0)
+}
+
+fn should_send(last_volume: f32, volume: f32) -> bool {
+    // always let it reach fully muted or full volume
+    (volume - last_volume).abs() > VOLUME_THRESHOLD
+        || ((volume == 0.0 || volume == 1.0) && volume != last_volume)
+}
+
+#[test]
+fn test_should_send() {
+    assert!(!should_send(0.5, 0.505));
+    assert!(should_send(0.5, 0.52));
+    assert!(should_send(0.005, 0.0));
+    assert!(should_send(0.995, 1.0));
+    assert!(!should_send(0.0, 0.0));
+}
@@ -1,26 +1,37 @@
 use super::EntityManager;
-use crate::cef::RustRefBrowser;
-use classicube_sys::*;
+use crate::{
+    cef::{RustRect, RustRefBrowser},
+    metrics,
+};
 use log::warn;
-use std::os::raw::{c_int, c_void};
+use std::{
+    os::raw::{c_int, c_void},
+    slice,
+};
 
 /// This gets called from cef browser's OnPaint
 pub extern "C" fn cef_paint_callback(
     browser: RustRefBrowser,
+    dirty_rects: *const RustRect,
+    dirty_rects_count: c_int,
     new_pixels: *const c_void,
     new_width: c_int,
     new_height: c_int,
 ) {
     let browser_id = browser.get_identifier();
+    metrics::record_paint(browser_id);
 
     if let Err(e) = EntityManager::with_by_browser_id(browser_id, |entity| {
-        let part = Bitmap {
-            Scan0: new_pixels as *mut _,
-            Width: new_width as i32,
-            Height: new_height as i32,
+        let (width, height) = (new_width as usize, new_height as usize);
+        let pixels = unsafe { slice::from_raw_parts(new_pixels as *const u8, 4 * width * height) };
+
+        let dirty_rects = if dirty_rects.is_null() || dirty_rects_count <= 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(dirty_rects, dirty_rects_count as usize) }
         };
 
-        entity.update_texture(part);
+        entity.paint(pixels, width, height, dirty_rects);
 
         Ok(())
     }) {
@@ -1,9 +1,15 @@
+use super::{EntityManager, StatsHud};
 use crate::helpers::*;
 use classicube_helpers::events::gfx::{ContextLostEventHandler, ContextRecreatedEventHandler};
 use classicube_sys::{
     OwnedGfxVertexBuffer, VertexFormat__VERTEX_FORMAT_P3FC4B, VertexFormat__VERTEX_FORMAT_P3FT2FC4B,
 };
 use log::debug;
+use std::cell::Cell;
+
+thread_local!(
+    static CONTEXT_LOST: Cell<bool> = Cell::new(false);
+);
 
 pub struct ContextHandler {
     context_lost_handler: ContextLostEventHandler,
@@ -18,7 +24,14 @@ impl ContextHandler {
         }
     }
 
+    /// textures can't be made until the context is recreated
+    pub fn is_context_lost() -> bool {
+        CONTEXT_LOST.with(|cell| cell.get())
+    }
+
     fn context_recreated() {
+        CONTEXT_LOST.with(|cell| cell.set(false));
+
         // create texture, vertex buffers
 
         QUAD_VB.with(|cell| {
@@ -34,9 +47,26 @@ impl ContextHandler {
                 4,
             ));
         });
+
+        EntityManager::with_all_entities(|entities| {
+            for entity in entities.values_mut() {
+                entity.on_context_recreated();
+            }
+        });
     }
 
     fn context_lost() {
+        CONTEXT_LOST.with(|cell| cell.set(true));
+
+        // labels are remade when we render again
+        StatsHud::clear();
+        EntityManager::with_all_entities(|entities| {
+            for entity in entities.values_mut() {
+                entity.clear_label();
+                entity.on_context_lost();
+            }
+        });
+
         // delete vertex buffers
         QUAD_VB.with(|cell| {
             cell.borrow_mut().take();
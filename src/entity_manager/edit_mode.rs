@@ -0,0 +1,316 @@
+use super::{
+    overlay::{self, Overlay},
+    placement::{get_center, set_center},
+    transform::{self, Transform},
+    EntityManager,
+};
+use crate::{chat::Chat, error::*};
+use classicube_sys::{
+    cc_bool, Blocks, Key_, Key_Pressed, Key__KEY_ESCAPE, Key__KEY_LMOUSE, Key__KEY_R,
+    Key__KEY_RMOUSE, PackedCol_Make, ScreenVTABLE, Vec3,
+};
+use log::warn;
+use nalgebra::Vector3;
+use std::{
+    cell::RefCell,
+    os::raw::{c_float, c_int, c_void},
+};
+
+// how far away from our eyes a screen can be held
+const MIN_DISTANCE: f32 = 1.0;
+const MAX_DISTANCE: f32 = 16.0;
+
+// how much one scroll wheel notch grows or shrinks the screen
+const SCROLL_SCALE: f32 = 1.1;
+
+// how many degrees the rotate key turns the screen
+const ROTATE_STEP: f32 = 15.0;
+
+struct Editing {
+    entity_id: usize,
+    /// distance from our eyes to the screen's center
+    distance: f32,
+    /// extra yaw on top of facing the player
+    yaw_offset: f32,
+    /// where the screen was before we started, put back if cancelled
+    original: Transform,
+}
+
+thread_local!(
+    static EDITING: RefCell<Option<Editing>> = RefCell::new(None);
+);
+
+// takes our keys and scrolling while editing
+thread_local!(
+    static OVERLAY: RefCell<Option<Overlay>> = RefCell::new(None);
+);
+
+// can place, can delete for every block from before we started editing,
+// put back once the mouse buttons are let go
+thread_local!(
+    static SAVED_PERMISSIONS: RefCell<Option<(Vec<cc_bool>, Vec<cc_bool>)>> = RefCell::new(None);
+);
+
+static V_TABLE: ScreenVTABLE = ScreenVTABLE {
+    Init: Some(overlay::no_op),
+    Update: Some(overlay::no_op_delta),
+    Free: Some(overlay::no_op),
+    Render: Some(overlay::no_op_delta),
+    BuildMesh: Some(overlay::no_op),
+    HandlesInputDown: Some(handles_input_down),
+    OnInputUp: Some(overlay::no_op_key),
+    HandlesKeyPress: Some(overlay::ignore_char),
+    HandlesTextChanged: Some(overlay::ignore_text),
+    HandlesPointerDown: Some(overlay::ignore_pointer),
+    OnPointerUp: Some(overlay::no_op_pointer),
+    HandlesPointerMove: Some(overlay::ignore_pointer),
+    HandlesMouseScroll: Some(handles_mouse_scroll),
+    Layout: Some(overlay::no_op),
+    ContextLost: Some(overlay::no_op),
+    ContextRecreated: Some(overlay::no_op),
+};
+
+/// returning 1 keeps the key from the game, so clicking doesn't break
+/// blocks, R doesn't respawn and escape doesn't open the pause menu
+unsafe extern "C" fn handles_input_down(_elem: *mut c_void, key: c_int) -> c_int {
+    #[allow(non_upper_case_globals)]
+    match key as Key_ {
+        Key__KEY_LMOUSE => EditMode::stop(true),
+        Key__KEY_RMOUSE | Key__KEY_ESCAPE => EditMode::stop(false),
+        // holding it keeps turning
+        Key__KEY_R => EditMode::rotate(ROTATE_STEP),
+        // we can still walk and look around
+        _ => return 0,
+    }
+
+    1
+}
+
+/// keeps the hotbar from changing
+unsafe extern "C" fn handles_mouse_scroll(_elem: *mut c_void, delta: c_float) -> c_int {
+    EditMode::scale(SCROLL_SCALE.powf(delta));
+
+    1
+}
+
+/// Lets the local player hold a screen in front of them to position it
+///
+/// The screen follows the crosshair, the scroll wheel scales it,
+/// R rotates it, left click commits and right click or escape cancels.
+pub struct EditMode {}
+
+impl EditMode {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn initialize(&mut self) {
+        OVERLAY.with(|cell| {
+            *cell.borrow_mut() = Some(Overlay::new(&V_TABLE));
+        });
+    }
+
+    pub fn shutdown(&mut self) {
+        Self::stop(false);
+        Self::restore_permissions();
+
+        OVERLAY.with(|cell| {
+            cell.borrow_mut().take();
+        });
+    }
+
+    pub fn is_editing() -> bool {
+        EDITING.with(|cell| cell.borrow().is_some())
+    }
+
+    pub fn start(entity_id: usize, eye_position: Vec3) -> Result<()> {
+        // put back anything we were editing before
+        Self::stop(false);
+
+        let (original, center) = EntityManager::with_by_entity_id(entity_id, |entity| {
+            entity.set_outline(Some(PackedCol_Make(255, 255, 0, 255)));
+
+            Ok((Transform::from_entity(entity), get_center(entity)))
+        })?;
+
+        let eye = Vector3::new(eye_position.X, eye_position.Y, eye_position.Z);
+        let distance = (center - eye).norm().max(MIN_DISTANCE).min(MAX_DISTANCE);
+
+        EDITING.with(|cell| {
+            *cell.borrow_mut() = Some(Editing {
+                entity_id,
+                distance,
+                yaw_offset: 0.0,
+                original,
+            });
+        });
+
+        Self::set_overlay(true);
+        Self::take_permissions();
+
+        Chat::print("editing screen: left click to place, right click to cancel");
+        Chat::print("scroll to resize, R to rotate");
+
+        Ok(())
+    }
+
+    /// Stops editing, either sending the new transform to everyone
+    /// or putting the screen back where it was
+    pub fn stop(commit: bool) {
+        let maybe_editing = EDITING.with(|cell| cell.borrow_mut().take());
+
+        if let Some(Editing {
+            entity_id,
+            original,
+            ..
+        }) = maybe_editing
+        {
+            Self::set_overlay(false);
+
+            let result = EntityManager::with_by_entity_id(entity_id, |entity| {
+                entity.set_outline(None);
+
+                let transform = Transform::from_entity(entity);
+
+                // everyone (including us) applies the change from chat
+                // so that it lands in everyone's undo history the same way
+                original.apply(entity)?;
+
+                Ok(transform)
+            });
+
+            match result {
+                Ok(transform) if commit => Self::send_transform(entity_id, transform),
+                Ok(_) => {}
+                Err(e) => warn!("edit_mode stop: {}", e),
+            }
+        }
+    }
+
+    /// one command so it's one undo step and can't be cut short
+    fn send_transform(entity_id: usize, transform: Transform) {
+        let Transform {
+            position: [x, y, z],
+            angles: [pitch, yaw, roll],
+            size: [width, height],
+        } = transform;
+
+        Chat::send(format!(
+            "cef transform {} {:.3} {:.3} {:.3} {:.1} {:.1} {:.1} {:.3} {:.3}",
+            entity_id, x, y, z, pitch, yaw, roll, width, height
+        ));
+    }
+
+    fn set_overlay(added: bool) {
+        OVERLAY.with(|cell| {
+            if let Some(overlay) = cell.borrow_mut().as_mut() {
+                if added {
+                    overlay.add();
+                } else {
+                    overlay.remove();
+                }
+            }
+        });
+    }
+
+    /// The game breaks and places blocks for as long as a mouse button is
+    /// held, so we take away permission instead of just the click.
+    fn take_permissions() {
+        SAVED_PERMISSIONS.with(|cell| {
+            let saved = &mut *cell.borrow_mut();
+            if saved.is_some() {
+                return;
+            }
+
+            unsafe {
+                *saved = Some((Blocks.CanPlace.to_vec(), Blocks.CanDelete.to_vec()));
+
+                for can in Blocks
+                    .CanPlace
+                    .iter_mut()
+                    .chain(Blocks.CanDelete.iter_mut())
+                {
+                    *can = 0;
+                }
+            }
+        });
+    }
+
+    fn restore_permissions() {
+        SAVED_PERMISSIONS.with(|cell| {
+            if let Some((can_place, can_delete)) = cell.borrow_mut().take() {
+                unsafe {
+                    Blocks.CanPlace.copy_from_slice(&can_place);
+                    Blocks.CanDelete.copy_from_slice(&can_delete);
+                }
+            }
+        });
+    }
+
+    /// Called every frame to make the screen follow the crosshair
+    pub fn update(eye_position: Vec3, [pitch, yaw]: [f32; 2]) {
+        // the click that stopped editing shouldn't break a block
+        if !Self::is_editing() {
+            let released = unsafe {
+                Key_Pressed[Key__KEY_LMOUSE as usize] == 0
+                    && Key_Pressed[Key__KEY_RMOUSE as usize] == 0
+            };
+            if released {
+                Self::restore_permissions();
+            }
+        }
+
+        let maybe_editing = EDITING.with(|cell| {
+            cell.borrow()
+                .as_ref()
+                .map(|editing| (editing.entity_id, editing.distance, editing.yaw_offset))
+        });
+
+        if let Some((entity_id, distance, yaw_offset)) = maybe_editing {
+            let dir = Vec3::get_dir_vector(yaw.to_radians(), pitch.to_radians());
+            let center = Vector3::new(
+                eye_position.X + dir.X * distance,
+                eye_position.Y + dir.Y * distance,
+                eye_position.Z + dir.Z * distance,
+            );
+
+            if let Err(e) = EntityManager::with_by_entity_id(entity_id, |entity| {
+                let [_, _, roll] = entity.get_angles();
+
+                // turn it to face the player
+                entity.set_angles([
+                    360.0 - pitch,
+                    (yaw + 180.0 + yaw_offset).rem_euclid(360.0),
+                    roll,
+                ]);
+                set_center(entity, center);
+
+                Ok(())
+            }) {
+                // our screen was removed, give back our keys and scrolling;
+                // block permissions come back next frame like after stop
+                warn!("edit_mode update: {}", e);
+                EDITING.with(|cell| cell.borrow_mut().take());
+                Self::set_overlay(false);
+            }
+        }
+    }
+
+    fn rotate(degrees: f32) {
+        EDITING.with(|cell| {
+            if let Some(editing) = cell.borrow_mut().as_mut() {
+                editing.yaw_offset = (editing.yaw_offset + degrees).rem_euclid(360.0);
+            }
+        });
+    }
+
+    fn scale(factor: f32) {
+        let maybe_entity_id = EDITING.with(|cell| cell.borrow().as_ref().map(|e| e.entity_id));
+
+        if let Some(entity_id) = maybe_entity_id {
+            let _ignore_error = EntityManager::with_by_entity_id(entity_id, |entity| {
+                transform::grow(entity, factor)
+            });
+        }
+    }
+}
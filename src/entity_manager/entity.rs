@@ -1,15 +1,82 @@
-use super::{TEXTURE_HEIGHT, TEXTURE_WIDTH};
+use super::{
+    context_handler::ContextHandler,
+    frame_mailbox::{FrameMailbox, FrameStats},
+    label::{make_label_text, Label, LabelQuad},
+    transform::{angles_from_rotation, rotation_from_angles, Transform, TransformHistory},
+    TEXTURE_HEIGHT, TEXTURE_WIDTH,
+};
 use crate::{
-    cef::RustRefBrowser,
+    cef::{Capture, Cef, RustRect, RustRefBrowser, CEF_DEFAULT_HEIGHT, CEF_DEFAULT_WIDTH},
     entity_manager::{MODEL_HEIGHT, MODEL_WIDTH},
+    error::*,
+    metrics,
     players::Player,
 };
 use classicube_sys::{
-    cc_bool, cc_int16, Bitmap, Entity, EntityVTABLE, Entity_Init, Entity_SetModel,
-    Gfx_UpdateTexturePart, LocationUpdate, Model_Render, OwnedGfxTexture, OwnedString, PackedCol,
-    Texture, TextureRec, PACKEDCOL_WHITE,
+    cc_bool, cc_int16, Bitmap, Entity, EntityVTABLE, Entity_Init, Entity_SetModel, Env,
+    Gfx_UpdateTexturePart, Lighting_Col, LocationUpdate, Model_Render, OwnedGfxTexture,
+    OwnedString, PackedCol, PackedCol_Make, PackedCol_Tint, Texture, TextureRec, Vec3, World,
+    PACKEDCOL_WHITE,
 };
-use std::{mem, pin::Pin};
+use log::warn;
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, mem, os::raw::c_int, pin::Pin, slice, time::Instant};
+
+// RotZ for a roll of 0
+const BASE_ROT_Z: f32 = 180.0;
+
+// width, height in blocks
+const DEFAULT_SIZE: [f32; 2] = [4.0, 2.25];
+const MIN_SIZE: f32 = 0.01;
+const MAX_SIZE: f32 = 512.0;
+
+// label text height and distance above the screen in blocks
+const LABEL_HEIGHT: f32 = 0.25;
+const LABEL_GAP: f32 = 0.1;
+
+// color of the entity currently being rendered, read back in get_col
+thread_local!(
+    static RENDER_COL: Cell<PackedCol> = Cell::new(PACKEDCOL_WHITE);
+);
+
+// if we're looking at the back of a FlatBack entity, read back in CefModel::draw
+thread_local!(
+    pub static RENDER_BACK_COL: Cell<Option<PackedCol>> = Cell::new(None);
+);
+
+// outline color of the entity currently being rendered, read back in CefModel::draw
+thread_local!(
+    pub static RENDER_OUTLINE_COL: Cell<Option<PackedCol>> = Cell::new(None);
+);
+
+// label of the entity currently being rendered, read back in CefModel::draw
+thread_local!(
+    pub static RENDER_LABEL: Cell<Option<LabelQuad>> = Cell::new(None);
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FaceMode {
+    /// only the front is drawn
+    SingleSided,
+    /// the back shows the page mirrored
+    DoubleSidedMirrored,
+    /// the back is a flat color
+    DoubleSidedFlat([u8; 3]),
+}
+
+/// what screens looked like before face modes
+impl Default for FaceMode {
+    fn default() -> Self {
+        FaceMode::SingleSided
+    }
+}
+
+impl FaceMode {
+    pub fn is_double_sided(self) -> bool {
+        self != FaceMode::SingleSided
+    }
+}
 
 pub struct CefEntity {
     pub id: usize,
@@ -18,8 +85,43 @@ pub struct CefEntity {
     pub browser: Option<RustRefBrowser>,
     pub player: Player,
 
+    created_at: Instant,
+
     v_table: Pin<Box<EntityVTABLE>>,
-    texture: OwnedGfxTexture,
+    /// None while the graphics context is lost or we're limited
+    texture: Option<OwnedGfxTexture>,
+    texture_size: (usize, usize),
+    frames: FrameMailbox,
+    /// over our screen limits, so not drawn and without a texture
+    limited: bool,
+
+    // 0-1
+    opacity: f32,
+    tint: [u8; 3],
+    /// use the map's lighting instead of always being full bright
+    lit: bool,
+    face_mode: FaceMode,
+    outline: Option<PackedCol>,
+
+    /// optional name given with `cef name`
+    name: Option<String>,
+    /// the page's title
+    title: String,
+    /// player who created this screen
+    owner: Option<String>,
+    /// if we saw the owner create it, synced owners could be made up
+    owner_trusted: bool,
+    /// player whose url we're waiting to accept
+    pending_requester: Option<String>,
+    label: Option<Label>,
+
+    /// page size in pixels from `cef resize`, used while it matches our
+    /// aspect ratio
+    resolution: Option<(usize, usize)>,
+    /// browser resolution multiplier picked by Lod, 0-1
+    resolution_scale: f32,
+
+    transform_history: TransformHistory,
 }
 
 impl CefEntity {
@@ -35,30 +137,41 @@ impl CefEntity {
             RenderName: Some(Self::render_name),
         });
 
-        let mut pixels: Vec<u8> = vec![255; 4 * TEXTURE_WIDTH * TEXTURE_HEIGHT];
-
-        let mut bmp = Bitmap {
-            Scan0: pixels.as_mut_ptr(),
-            Width: TEXTURE_WIDTH as i32,
-            Height: TEXTURE_HEIGHT as i32,
-        };
-
-        let texture = OwnedGfxTexture::create(&mut bmp, true, false);
-
         let mut this = Self {
             id,
             entity,
             v_table,
-            texture,
+            texture: None,
+            texture_size: (0, 0),
+            frames: FrameMailbox::new(),
+            limited: false,
             browser: None,
             player,
+            created_at: Instant::now(),
+            opacity: 1.0,
+            tint: [255, 255, 255],
+            lit: false,
+            face_mode: FaceMode::default(),
+            outline: None,
+            name: None,
+            title: String::new(),
+            owner: None,
+            owner_trusted: false,
+            resolution: None,
+            resolution_scale: 1.0,
+            pending_requester: None,
+            label: None,
+            transform_history: Default::default(),
         };
 
         unsafe {
             this.register_entity();
         }
 
-        this.set_scale(0.25);
+        let (width, height) = get_resolution_for_size(DEFAULT_SIZE);
+        this.create_texture(width, height);
+
+        this.set_size(DEFAULT_SIZE).unwrap();
 
         this
     }
@@ -75,7 +188,7 @@ impl CefEntity {
     }
 
     unsafe extern "C" fn get_col(_entity: *mut Entity) -> PackedCol {
-        PACKEDCOL_WHITE
+        RENDER_COL.with(|cell| cell.get())
     }
 
     unsafe extern "C" fn c_render_model(_entity: *mut Entity, _delta_time: f64, _t: f32) {
@@ -86,10 +199,7 @@ impl CefEntity {
 
     unsafe fn register_entity(&mut self) {
         let CefEntity {
-            entity,
-            v_table,
-            texture,
-            ..
+            entity, v_table, ..
         } = self;
 
         Entity_Init(entity);
@@ -102,13 +212,12 @@ impl CefEntity {
 
         entity.VTABLE = v_table.as_mut().get_unchecked_mut();
         entity.Velocity.set(0.0, 0.0, 0.0);
-        entity.RotZ = 180.0;
-        entity.TextureId = texture.resource_id;
+        entity.RotZ = BASE_ROT_Z;
 
         entity.Position.set(0.0, 0.0, 0.0);
 
         // hack so that Model can see browser resolution sizes
-        // that are updated in update_texture
+        // that are updated in update_texture, ID is set in create_texture
         entity.NameTex = Texture {
             ID: entity.TextureId,
             X: -(MODEL_WIDTH as cc_int16 / 2),
@@ -124,32 +233,681 @@ impl CefEntity {
         };
     }
 
-    pub fn update_texture(&mut self, mut part: Bitmap) {
+    /// (Re)creates a white texture big enough for a `width` x `height` frame
+    fn create_texture(&mut self, width: usize, height: usize) {
+        let (texture_width, texture_height) = get_texture_size(width, height);
+        self.texture_size = (texture_width, texture_height);
+
+        // free the old one first so we never hold both
+        self.texture.take();
+
+        // made in on_context_recreated instead
+        if ContextHandler::is_context_lost() {
+            return;
+        }
+
+        // must be a vec or else we try to fit huge array onto stack and crash!
+        let mut pixels: Vec<u8> = vec![255; 4 * texture_width * texture_height];
+
+        let mut bmp = Bitmap {
+            Scan0: pixels.as_mut_ptr(),
+            Width: texture_width as i32,
+            Height: texture_height as i32,
+        };
+
+        let texture = OwnedGfxTexture::create(&mut bmp, true, false);
+        self.entity.TextureId = texture.resource_id;
+        self.entity.NameTex.ID = texture.resource_id;
+
+        self.texture = Some(texture);
+    }
+
+    /// Frees our texture before the graphics context goes away
+    pub fn on_context_lost(&mut self) {
+        self.texture.take();
+    }
+
+    /// Remakes our texture and asks the browser to paint everything again
+    pub fn on_context_recreated(&mut self) {
+        if self.limited {
+            return;
+        }
+
+        let (width, height) = self.texture_size;
+        self.create_texture(width, height);
+
+        if let Some(browser) = &self.browser {
+            if let Err(e) = browser.invalidate() {
+                warn!("invalidate: {}", e);
+            }
+        }
+    }
+
+    /// Called from the browser's OnPaint, the frame is uploaded in `upload_frame`
+    pub fn paint(&mut self, pixels: &[u8], width: usize, height: usize, dirty_rects: &[RustRect]) {
+        self.frames.paint(pixels, width, height, dirty_rects);
+    }
+
+    /// Uploads the latest painted frame if there's a new one
+    pub fn upload_frame(&mut self) {
+        let mut frame = match self.frames.take() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let bitmap = Bitmap {
+            Scan0: frame.pixels.as_mut_ptr(),
+            Width: frame.width as i32,
+            Height: frame.height as i32,
+        };
+        self.update_texture(bitmap, &frame.dirty_rects);
+
+        self.frames.recycle(frame);
+    }
+
+    pub fn get_frame_stats(&mut self) -> FrameStats {
+        self.frames.get_stats()
+    }
+
+    /// A copy of the last frame the browser painted
+    pub fn capture_frame(&self) -> Option<Capture> {
+        self.frames
+            .latest()
+            .map(|frame| Capture::from_bgra(&frame.pixels, frame.width, frame.height))
+    }
+
+    /// Uploads the `dirty_rects` parts of `frame`, or all of it if there are none
+    ///
+    /// Our texture is reallocated when the browser was resized to a size
+    /// that needs a different power of two.
+    fn update_texture(&mut self, mut frame: Bitmap, dirty_rects: &[[c_int; 4]]) {
+        if ContextHandler::is_context_lost() || self.limited {
+            return;
+        }
+
+        let (width, height) = (frame.Width as usize, frame.Height as usize);
+        let reallocated =
+            self.texture.is_none() || get_texture_size(width, height) != self.texture_size;
+        if reallocated {
+            self.create_texture(width, height);
+        }
+
+        let resource_id = match &self.texture {
+            Some(texture) => texture.resource_id,
+            None => return,
+        };
+
         // update uv's
-        self.entity.NameTex.uv.U2 = part.Width as f32 / TEXTURE_WIDTH as f32;
-        self.entity.NameTex.uv.V2 = part.Height as f32 / TEXTURE_HEIGHT as f32;
+        let (texture_width, texture_height) = self.texture_size;
+        self.entity.NameTex.uv.U2 = width as f32 / texture_width as f32;
+        self.entity.NameTex.uv.V2 = height as f32 / texture_height as f32;
 
-        unsafe {
-            Gfx_UpdateTexturePart(self.texture.resource_id, 0, 0, &mut part, 0);
+        // a new texture is blank, so it needs the whole frame
+        if reallocated || dirty_rects.is_empty() {
+            unsafe {
+                Gfx_UpdateTexturePart(resource_id, 0, 0, &mut frame, 0);
+            }
+            self.record_upload(4 * width * height);
+            return;
+        }
+
+        let pixels = unsafe {
+            slice::from_raw_parts(frame.Scan0, 4 * (frame.Width * frame.Height) as usize)
+        };
+
+        for rect in dirty_rects {
+            let [x, y, width, height] =
+                match clamp_rect(*rect, frame.Width as c_int, frame.Height as c_int) {
+                    Some(rect) => rect,
+                    None => continue,
+                };
+
+            // Gfx_UpdateTexturePart wants tightly packed rows
+            let mut rect_pixels = copy_rect(pixels, frame.Width as usize, [x, y, width, height]);
+            let mut part = Bitmap {
+                Scan0: rect_pixels.as_mut_ptr(),
+                Width: width as i32,
+                Height: height as i32,
+            };
+
+            unsafe {
+                Gfx_UpdateTexturePart(resource_id, x, y, &mut part, 0);
+            }
+            self.record_upload(rect_pixels.len());
+        }
+    }
+
+    fn record_upload(&self, bytes: usize) {
+        if let Some(browser) = &self.browser {
+            metrics::record_upload(browser.get_identifier(), bytes);
         }
     }
 
-    pub fn render_model(&mut self) {
+    /// `is_target` if this is the screen `cef` commands will affect
+    pub fn render_model(&mut self, eye_position: Vec3, is_target: bool) {
+        if self.texture.is_none() {
+            return;
+        }
+
+        let back_col = if self.is_front_facing(eye_position) {
+            None
+        } else {
+            match self.face_mode {
+                FaceMode::SingleSided => return,
+                FaceMode::DoubleSidedMirrored => None,
+                FaceMode::DoubleSidedFlat(color) => Some(self.get_col_with_tint(color)),
+            }
+        };
+
+        let col = self.get_render_col();
+        RENDER_COL.with(|cell| cell.set(col));
+        RENDER_BACK_COL.with(|cell| cell.set(back_col));
+
+        let outline = self.outline.or_else(|| {
+            if is_target && Label::is_enabled() {
+                Some(PACKEDCOL_WHITE)
+            } else {
+                None
+            }
+        });
+        RENDER_OUTLINE_COL.with(|cell| cell.set(outline));
+
+        let label_quad = if Label::is_enabled() {
+            self.update_label();
+            self.get_label_quad()
+        } else {
+            None
+        };
+        RENDER_LABEL.with(|cell| cell.set(label_quad));
+
         let entity = self.entity.as_mut();
         unsafe {
             Model_Render(entity.Model, entity.get_unchecked_mut());
         }
     }
 
-    pub fn set_scale(&mut self, scale: f32) {
+    /// remake our label texture if its text changed
+    fn update_label(&mut self) {
+        let text = make_label_text(
+            self.id,
+            self.name.as_deref(),
+            &self.title,
+            self.owner.as_deref(),
+        );
+
+        if self.label.as_ref().map(|label| label.get_text()) != Some(text.as_str()) {
+            self.label = Some(Label::new(text));
+        }
+    }
+
+    /// Centered above the top of the screen, sized in blocks no matter our scale
+    fn get_label_quad(&self) -> Option<LabelQuad> {
+        let label = self.label.as_ref()?;
+        let scale = self.entity.ModelScale;
+
+        let height = LABEL_HEIGHT / scale.Y;
+        let width = LABEL_HEIGHT * label.get_aspect() / scale.X;
+        let gap = LABEL_GAP / scale.Y;
+
+        // -y is up in model space
+        Some(LabelQuad {
+            texture: label.texture,
+            x: -width / 2.0,
+            y: -(MODEL_HEIGHT as f32) - gap - height,
+            width,
+            height,
+        })
+    }
+
+    /// forget our label texture, it gets remade on the next render
+    pub fn clear_label(&mut self) {
+        self.label.take();
+    }
+
+    fn get_render_col(&self) -> PackedCol {
+        self.get_col_with_tint(self.tint)
+    }
+
+    fn get_col_with_tint(&self, [r, g, b]: [u8; 3]) -> PackedCol {
+        let a = (self.opacity * 255.0) as u8;
+        let col = PackedCol_Make(r, g, b, a);
+
+        if self.lit {
+            unsafe { PackedCol_Tint(col, self.get_light_col()) }
+        } else {
+            col
+        }
+    }
+
+    /// light color of the map at our position
+    fn get_light_col(&self) -> PackedCol {
+        let pos = self.entity.Position;
+        let (x, y, z) = (
+            pos.X.floor() as i32,
+            pos.Y.floor() as i32,
+            pos.Z.floor() as i32,
+        );
+
+        unsafe {
+            if x >= 0 && y >= 0 && z >= 0 && x < World.Width && y < World.Height && z < World.Length
+            {
+                Lighting_Col(x, y, z)
+            } else {
+                Env.SunCol
+            }
+        }
+    }
+
+    /// opacity is a float between 0-1
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn set_tint(&mut self, tint: [u8; 3]) {
+        self.tint = tint;
+    }
+
+    pub fn get_tint(&self) -> [u8; 3] {
+        self.tint
+    }
+
+    pub fn set_lit(&mut self, lit: bool) {
+        self.lit = lit;
+    }
+
+    pub fn get_lit(&self) -> bool {
+        self.lit
+    }
+
+    pub fn set_face_mode(&mut self, face_mode: FaceMode) {
+        self.face_mode = face_mode;
+    }
+
+    pub fn get_face_mode(&self) -> FaceMode {
+        self.face_mode
+    }
+
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    /// `trusted` if we saw them run the command ourselves
+    pub fn set_owner(&mut self, owner: Option<String>, trusted: bool) {
+        self.owner = owner;
+        self.owner_trusted = trusted;
+    }
+
+    /// for showing and syncing, use `get_trusted_owner` for permissions
+    pub fn get_owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    pub fn get_trusted_owner(&self) -> Option<&str> {
+        if self.owner_trusted {
+            self.get_owner()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_pending_requester(&mut self, requester: Option<String>) {
+        self.pending_requester = requester;
+    }
+
+    pub fn get_pending_requester(&self) -> Option<&str> {
+        self.pending_requester.as_deref()
+    }
+
+    pub fn get_created_at(&self) -> Instant {
+        self.created_at
+    }
+
+    /// how much texture memory we use when shown
+    pub fn get_texture_bytes(&self) -> usize {
+        let (width, height) = self.texture_size;
+        4 * width * height
+    }
+
+    /// Frees our texture while we're over the screen limits, lod hides the
+    /// browser and the texture comes back with its next paint
+    pub fn set_limited(&mut self, limited: bool) {
+        self.limited = limited;
+
+        if limited {
+            self.texture.take();
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.limited
+    }
+
+    /// draw an outline around the screen, used while editing
+    pub fn set_outline(&mut self, outline: Option<PackedCol>) {
+        self.outline = outline;
+    }
+
+    pub fn get_outline(&self) -> Option<PackedCol> {
+        self.outline
+    }
+
+    /// pitch, yaw, roll in degrees
+    pub fn set_angles(&mut self, [pitch, yaw, roll]: [f32; 3]) {
         let CefEntity { entity, .. } = self;
 
-        // TODO make 1.0 be 1 block wide
-        entity.ModelScale.set(scale, scale, 1.0);
+        entity.RotX = pitch;
+        entity.RotY = yaw;
+        // our model is drawn upside down without this
+        entity.RotZ = BASE_ROT_Z + roll;
     }
 
-    pub fn get_scale(&self) -> f32 {
+    /// pitch, yaw, roll in degrees
+    pub fn get_angles(&self) -> [f32; 3] {
         let CefEntity { entity, .. } = self;
-        entity.ModelScale.X
+
+        [entity.RotX, entity.RotY, entity.RotZ - BASE_ROT_Z]
+    }
+
+    /// Same order as the game's entity transform: roll, then pitch, then yaw
+    pub fn get_rotation(&self) -> UnitQuaternion<f32> {
+        rotation_from_angles(self.get_angles())
+    }
+
+    pub fn set_rotation(&mut self, rotation: UnitQuaternion<f32>) {
+        self.set_angles(angles_from_rotation(rotation));
+    }
+
+    /// the direction the front of the screen faces
+    pub fn get_normal(&self) -> Vector3<f32> {
+        // when angles 0 0 0, the back faces -z
+        self.get_rotation().transform_vector(&-Vector3::z())
+    }
+
+    /// if `eye_position` can see the front of the screen
+    pub fn is_front_facing(&self, eye_position: Vec3) -> bool {
+        let pos = self.entity.Position;
+        let to_eye = Vector3::new(
+            eye_position.X - pos.X,
+            eye_position.Y - pos.Y,
+            eye_position.Z - pos.Z,
+        );
+
+        to_eye.dot(&self.get_normal()) >= 0.0
+    }
+
+    /// Sets the width in blocks, keeping the current aspect ratio
+    pub fn set_scale(&mut self, scale: f32) -> Result<()> {
+        let [width, height] = self.get_size();
+
+        self.set_size([scale, scale * height / width])
+    }
+
+    /// Sets the width and height in blocks
+    ///
+    /// The browser's resolution is changed to match the new aspect ratio.
+    pub fn set_size(&mut self, size: [f32; 2]) -> Result<()> {
+        check_size(size)?;
+        let [width, height] = size;
+
+        let CefEntity { entity, .. } = self;
+
+        // our model is MODEL_WIDTH x MODEL_HEIGHT units, scale that into blocks
+        entity.ModelScale.set(
+            width / MODEL_WIDTH as f32,
+            height / MODEL_HEIGHT as f32,
+            1.0,
+        );
+
+        if let Err(e) = self.resize_browser_to_fit() {
+            warn!("resize_browser_to_fit: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// width and height in blocks
+    pub fn get_size(&self) -> [f32; 2] {
+        let CefEntity { entity, .. } = self;
+
+        [
+            entity.ModelScale.X * MODEL_WIDTH as f32,
+            entity.ModelScale.Y * MODEL_HEIGHT as f32,
+        ]
+    }
+
+    /// Remember the current position, angles and size so they can be undone
+    pub fn save_transform(&mut self) {
+        let transform = Transform::from_entity(self);
+        self.transform_history.push(transform);
     }
+
+    /// Runs `f`, remembering our transform from before it for undo
+    /// if it succeeded and changed something
+    pub fn change_transform<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let before = Transform::from_entity(self);
+        let result = f(self)?;
+
+        if Transform::from_entity(self) != before {
+            self.transform_history.push(before);
+        }
+
+        Ok(result)
+    }
+
+    pub fn undo_transform(&mut self) -> Result<()> {
+        let current = Transform::from_entity(self);
+        let previous = self
+            .transform_history
+            .undo(current)
+            .chain_err(|| "nothing to undo")?;
+        previous.apply(self)
+    }
+
+    pub fn redo_transform(&mut self) -> Result<()> {
+        let current = Transform::from_entity(self);
+        let next = self
+            .transform_history
+            .redo(current)
+            .chain_err(|| "nothing to redo")?;
+        next.apply(self)
+    }
+
+    /// Sets the page size in pixels
+    ///
+    /// Our height in blocks changes to match its aspect ratio.
+    pub fn set_resolution(&mut self, width: usize, height: usize) -> Result<()> {
+        let width = width.max(1).min(TEXTURE_WIDTH);
+        let height = height.max(1).min(TEXTURE_HEIGHT);
+
+        let [block_width, _] = self.get_size();
+        let size = [block_width, block_width * height as f32 / width as f32];
+        check_size(size)?;
+
+        self.resolution = Some((width, height));
+        self.set_size(size)
+    }
+
+    /// page size in pixels, Lod only lowers how many of them get painted
+    pub fn get_resolution(&self) -> (usize, usize) {
+        let size = self.get_size();
+
+        match self.resolution {
+            Some(resolution) if has_aspect_ratio(resolution, size) => resolution,
+            _ => get_resolution_for_size(size),
+        }
+    }
+
+    /// Paints smaller frames without changing the page's layout
+    pub fn set_resolution_scale(&mut self, scale: f32) -> Result<()> {
+        self.resolution_scale = scale.max(0.1).min(1.0);
+
+        if let Some(browser) = &self.browser {
+            Cef::set_browser_scale(browser, self.resolution_scale)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_resolution_scale(&self) -> f32 {
+        self.resolution_scale
+    }
+
+    /// Changes the browser's resolution to match our aspect ratio
+    pub fn resize_browser_to_fit(&self) -> Result<()> {
+        if let Some(browser) = &self.browser {
+            let (width, height) = self.get_resolution();
+            Cef::resize_browser(browser, width, height)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Smallest power of two texture that fits a `width` x `height` frame
+fn get_texture_size(width: usize, height: usize) -> (usize, usize) {
+    (
+        width.max(1).next_power_of_two().min(TEXTURE_WIDTH),
+        height.max(1).next_power_of_two().min(TEXTURE_HEIGHT),
+    )
+}
+
+/// `rect` limited to a `width` x `height` frame, None if nothing is left
+fn clamp_rect(
+    [x, y, rect_width, rect_height]: [c_int; 4],
+    width: c_int,
+    height: c_int,
+) -> Option<[c_int; 4]> {
+    let left = x.max(0);
+    let top = y.max(0);
+    let right = (x + rect_width).min(width);
+    let bottom = (y + rect_height).min(height);
+
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some([left, top, right - left, bottom - top])
+}
+
+/// Copies a BGRA sub-region out of a frame that is `frame_width` pixels wide
+fn copy_rect(pixels: &[u8], frame_width: usize, [x, y, width, height]: [c_int; 4]) -> Vec<u8> {
+    let (x, y, width, height) = (x as usize, y as usize, width as usize, height as usize);
+
+    let mut rect_pixels = Vec::with_capacity(4 * width * height);
+    for row in y..(y + height) {
+        let start = 4 * (row * frame_width + x);
+        rect_pixels.extend_from_slice(&pixels[start..(start + 4 * width)]);
+    }
+
+    rect_pixels
+}
+
+/// whether a `resolution` in pixels has about the same shape as `size` in blocks
+/// Sizes from chat and from syncing both have to pass this
+pub fn check_size(size: [f32; 2]) -> Result<()> {
+    for &length in &size {
+        if !length.is_finite() {
+            bail!("size must be a number");
+        }
+        if length < MIN_SIZE || length > MAX_SIZE {
+            bail!("size must be between {} and {} blocks", MIN_SIZE, MAX_SIZE);
+        }
+    }
+
+    Ok(())
+}
+
+fn has_aspect_ratio(
+    (width, height): (usize, usize),
+    [block_width, block_height]: [f32; 2],
+) -> bool {
+    let aspect = width as f32 / height as f32;
+    let block_aspect = block_width / block_height;
+
+    (aspect - block_aspect).abs() <= block_aspect * 0.01
+}
+
+/// Browser resolution with the same aspect ratio as `size`
+///
+/// Fits inside the default resolution on the longer side.
+fn get_resolution_for_size([width, height]: [f32; 2]) -> (usize, usize) {
+    let aspect = width / height;
+    let default_aspect = CEF_DEFAULT_WIDTH as f32 / CEF_DEFAULT_HEIGHT as f32;
+
+    let (width, height) = if aspect >= default_aspect {
+        let width = CEF_DEFAULT_WIDTH as f32;
+        (width, width / aspect)
+    } else {
+        let height = CEF_DEFAULT_HEIGHT as f32;
+        (height * aspect, height)
+    };
+
+    (
+        (width.round() as usize).max(1).min(TEXTURE_WIDTH),
+        (height.round() as usize).max(1).min(TEXTURE_HEIGHT),
+    )
+}
+
+#[test]
+fn test_get_resolution_for_size() {
+    assert_eq!(get_resolution_for_size([16.0, 9.0]), (1920, 1080));
+    assert_eq!(get_resolution_for_size([4.0, 2.25]), (1920, 1080));
+    assert_eq!(get_resolution_for_size([2.0, 2.0]), (1080, 1080));
+    assert_eq!(get_resolution_for_size([4.0, 1.0]), (1920, 480));
+    assert_eq!(get_resolution_for_size([1.0, 4.0]), (270, 1080));
+
+    assert!(has_aspect_ratio((1280, 720), [4.0, 2.25]));
+    assert!(has_aspect_ratio((800, 600), [4.0, 3.0]));
+    assert!(!has_aspect_ratio((800, 600), [4.0, 2.25]));
+}
+
+#[test]
+fn test_dirty_rects() {
+    assert_eq!(clamp_rect([1, 1, 10, 1], 3, 2), Some([1, 1, 2, 1]));
+    assert_eq!(clamp_rect([5, 0, 1, 1], 3, 2), None);
+
+    // 3x2 frame, each pixel's bytes are its index
+    let pixels: Vec<u8> = (0..6u8).flat_map(|i| vec![i; 4]).collect();
+    assert_eq!(
+        copy_rect(&pixels, 3, [1, 0, 2, 2]),
+        vec![1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 5, 5, 5, 5]
+    );
+}
+
+#[test]
+fn test_get_texture_size() {
+    assert_eq!(get_texture_size(640, 360), (1024, 512));
+    assert_eq!(get_texture_size(1920, 1080), (2048, 2048));
+    assert_eq!(get_texture_size(512, 512), (512, 512));
+    assert_eq!(get_texture_size(0, 1), (1, 1));
+}
+
+#[test]
+fn test_check_size() {
+    assert!(check_size(DEFAULT_SIZE).is_ok());
+    assert!(check_size([MAX_SIZE, MIN_SIZE]).is_ok());
+    assert!(check_size([0.0, 1.0]).is_err());
+    assert!(check_size([-1.0, 1.0]).is_err());
+    assert!(check_size([1.0, f32::NAN]).is_err());
+    assert!(check_size([f32::INFINITY, 1.0]).is_err());
+    assert!(check_size([MAX_SIZE * 2.0, 1.0]).is_err());
 }
@@ -0,0 +1,192 @@
+use crate::cef::RustRect;
+use std::{
+    mem,
+    os::raw::c_int,
+    time::{Duration, Instant},
+};
+
+// past this many rects it's cheaper to upload the whole frame
+const MAX_DIRTY_RECTS: usize = 16;
+
+// how often the per second stats are recomputed
+const STATS_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct Frame {
+    /// BGRA
+    pub pixels: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    /// x, y, width, height of the parts that changed since the last upload,
+    /// empty if everything did
+    pub dirty_rects: Vec<[c_int; 4]>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub paints_per_second: f32,
+    pub uploads_per_second: f32,
+    /// frames painted over before they were uploaded
+    pub dropped: usize,
+}
+
+/// Holds the latest frame a browser painted until the render hook uploads it
+///
+/// Painting while a frame is still waiting replaces it, so at most one frame
+/// is uploaded per game frame. The two buffers are swapped instead of
+/// reallocated.
+pub struct FrameMailbox {
+    /// latest painted frame
+    back: Frame,
+    /// the buffer being uploaded, or our spare
+    front: Option<Frame>,
+    has_frame: bool,
+
+    stats: FrameStats,
+    window_start: Instant,
+    window_paints: usize,
+    window_uploads: usize,
+}
+
+impl FrameMailbox {
+    pub fn new() -> Self {
+        Self {
+            back: Frame::default(),
+            front: Some(Frame::default()),
+            has_frame: false,
+            stats: FrameStats::default(),
+            window_start: Instant::now(),
+            window_paints: 0,
+            window_uploads: 0,
+        }
+    }
+
+    /// Copies a painted frame in, CEF reuses `pixels` after OnPaint returns
+    pub fn paint(&mut self, pixels: &[u8], width: usize, height: usize, dirty_rects: &[RustRect]) {
+        let frame = &mut self.back;
+
+        if self.has_frame {
+            self.stats.dropped += 1;
+
+            // the frame we're replacing was never uploaded,
+            // so its dirty parts still need to be
+            let full = frame.dirty_rects.is_empty()
+                || dirty_rects.is_empty()
+                || frame.width != width
+                || frame.height != height
+                || frame.dirty_rects.len() + dirty_rects.len() > MAX_DIRTY_RECTS;
+
+            if full {
+                frame.dirty_rects.clear();
+            } else {
+                frame
+                    .dirty_rects
+                    .extend(dirty_rects.iter().map(rect_to_array));
+            }
+        } else {
+            frame.dirty_rects.clear();
+            if dirty_rects.len() <= MAX_DIRTY_RECTS {
+                frame
+                    .dirty_rects
+                    .extend(dirty_rects.iter().map(rect_to_array));
+            }
+        }
+
+        frame.pixels.clear();
+        frame.pixels.extend_from_slice(pixels);
+        frame.width = width;
+        frame.height = height;
+
+        self.has_frame = true;
+        self.window_paints += 1;
+        self.update_stats();
+    }
+
+    /// The latest frame if there's a new one, give it back with `recycle`
+    pub fn take(&mut self) -> Option<Frame> {
+        if !self.has_frame {
+            return None;
+        }
+
+        let spare = self.front.take().unwrap_or_default();
+        let frame = mem::replace(&mut self.back, spare);
+        self.has_frame = false;
+
+        self.window_uploads += 1;
+        self.update_stats();
+
+        Some(frame)
+    }
+
+    pub fn recycle(&mut self, frame: Frame) {
+        self.front = Some(frame);
+    }
+
+    /// The last frame painted, uploaded or not
+    pub fn latest(&self) -> Option<&Frame> {
+        let frame = if self.has_frame {
+            Some(&self.back)
+        } else {
+            self.front.as_ref()
+        };
+
+        frame.filter(|frame| frame.width > 0 && frame.height > 0)
+    }
+
+    pub fn get_stats(&mut self) -> FrameStats {
+        self.update_stats();
+        self.stats
+    }
+
+    fn update_stats(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < STATS_WINDOW {
+            return;
+        }
+
+        let seconds = elapsed.as_secs_f32();
+        self.stats.paints_per_second = self.window_paints as f32 / seconds;
+        self.stats.uploads_per_second = self.window_uploads as f32 / seconds;
+
+        self.window_start = Instant::now();
+        self.window_paints = 0;
+        self.window_uploads = 0;
+    }
+}
+
+fn rect_to_array(rect: &RustRect) -> [c_int; 4] {
+    [rect.x, rect.y, rect.width, rect.height]
+}
+
+#[test]
+fn test_frame_mailbox() {
+    let rect = |x| RustRect {
+        x,
+        y: 0,
+        width: 1,
+        height: 1,
+    };
+
+    let mut mailbox = FrameMailbox::new();
+    assert!(mailbox.take().is_none());
+
+    mailbox.paint(&[1; 8], 2, 1, &[rect(0)]);
+    mailbox.paint(&[2; 8], 2, 1, &[rect(1)]);
+    assert_eq!(mailbox.get_stats().dropped, 1);
+
+    // only the latest pixels, but both dirty parts
+    let frame = mailbox.take().unwrap();
+    assert_eq!(frame.pixels, vec![2; 8]);
+    assert_eq!(frame.dirty_rects.len(), 2);
+    mailbox.recycle(frame);
+    assert!(mailbox.take().is_none());
+
+    // a full paint makes the waiting frame full
+    mailbox.paint(&[3; 8], 2, 1, &[rect(0)]);
+    mailbox.paint(&[4; 8], 2, 1, &[]);
+    mailbox.paint(&[5; 8], 2, 1, &[rect(1)]);
+    let frame = mailbox.take().unwrap();
+    assert_eq!(frame.pixels, vec![5; 8]);
+    assert!(frame.dirty_rects.is_empty());
+    assert_eq!(mailbox.get_stats().dropped, 3);
+}
@@ -0,0 +1,121 @@
+use classicube_sys::{
+    DrawTextArgs, DrawTextArgs_Make, Drawer2D_MakeFont, Drawer2D_MakeTextTexture, FontDesc,
+    Font_Free, Gfx_DeleteTexture, OwnedString, Texture,
+};
+use std::{cell::Cell, mem};
+
+// font size in pixels, the texture is scaled to LABEL_HEIGHT anyways
+const FONT_SIZE: i32 = 24;
+
+// longer titles get cut off
+const MAX_TITLE_LENGTH: usize = 48;
+
+thread_local!(
+    static ENABLED: Cell<bool> = Cell::new(false);
+);
+
+/// Where to draw a label, in model units
+#[derive(Clone, Copy)]
+pub struct LabelQuad {
+    pub texture: Texture,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A text texture drawn above a screen
+pub struct Label {
+    text: String,
+    pub texture: Texture,
+}
+
+impl Label {
+    pub fn new(text: String) -> Self {
+        let texture = unsafe {
+            let mut font: FontDesc = mem::zeroed();
+            // FONT_FLAGS_NONE
+            Drawer2D_MakeFont(&mut font, FONT_SIZE, 0);
+
+            let owned_string = OwnedString::new(text.clone());
+            let mut args: DrawTextArgs = mem::zeroed();
+            DrawTextArgs_Make(&mut args, owned_string.as_cc_string(), &mut font, 1);
+
+            let mut texture: Texture = mem::zeroed();
+            Drawer2D_MakeTextTexture(&mut texture, &mut args);
+
+            Font_Free(&mut font);
+
+            texture
+        };
+
+        Self { text, texture }
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// width / height
+    pub fn get_aspect(&self) -> f32 {
+        self.texture.Width as f32 / (self.texture.Height as f32).max(1.0)
+    }
+
+    /// Shows labels and the targeted screen's outline
+    pub fn set_enabled(enabled: bool) {
+        ENABLED.with(|cell| cell.set(enabled));
+    }
+
+    pub fn is_enabled() -> bool {
+        ENABLED.with(|cell| cell.get())
+    }
+}
+
+impl Drop for Label {
+    fn drop(&mut self) {
+        unsafe {
+            Gfx_DeleteTexture(&mut self.texture.ID);
+        }
+    }
+}
+
+/// `#id name - title (owner)` with chat color codes
+pub fn make_label_text(id: usize, name: Option<&str>, title: &str, owner: Option<&str>) -> String {
+    let mut text = format!("&e#{}", id);
+
+    if let Some(name) = name {
+        text.push_str(&format!(" &f{}", name));
+    }
+
+    if !title.is_empty() {
+        let title: String = if title.chars().count() > MAX_TITLE_LENGTH {
+            let mut title: String = title.chars().take(MAX_TITLE_LENGTH - 3).collect();
+            title.push_str("...");
+            title
+        } else {
+            title.to_string()
+        };
+
+        text.push_str(&format!(" &7- &f{}", title));
+    }
+
+    if let Some(owner) = owner {
+        text.push_str(&format!(" &7({})", owner));
+    }
+
+    text
+}
+
+#[test]
+fn test_make_label_text() {
+    assert_eq!(make_label_text(3, None, "", None), "&e#3");
+    assert_eq!(
+        make_label_text(3, Some("tv"), "Cool Video", Some("SpiralP")),
+        "&e#3 &ftv &7- &fCool Video &7(SpiralP)"
+    );
+
+    let long_title = "a".repeat(100);
+    let text = make_label_text(0, None, &long_title, None);
+    assert!(text.ends_with("..."));
+    assert_eq!(text.len(), "&e#0 &7- &f".len() + MAX_TITLE_LENGTH);
+}
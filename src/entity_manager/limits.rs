@@ -0,0 +1,308 @@
+use super::CefEntity;
+use crate::{chat::permissions, error::*, host};
+use classicube_helpers::tab_list::remove_color;
+use classicube_sys::{Vec3, ENTITIES_SELF_ID};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap, fs, io::ErrorKind as IoErrorKind, time::Instant};
+
+// stored next to cef.log
+const SETTINGS_PATH: &str = "cef-limits.json";
+
+thread_local!(
+    static LIMITS: RefCell<Limits> = RefCell::new(Limits::default());
+);
+
+/// Which screens to hide when there are too many
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Hide {
+    /// the screens created last
+    Newest,
+    /// the screens created first
+    Oldest,
+    /// the screens farthest from us
+    Farthest,
+}
+
+impl Hide {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "newest" | "new" => Hide::Newest,
+            "oldest" | "old" => Hide::Oldest,
+            "farthest" | "furthest" => Hide::Farthest,
+            _ => bail!("expected newest, oldest or farthest"),
+        })
+    }
+}
+
+/// How many screens we draw at once
+///
+/// Screens over the limits still exist and stay in sync, we just don't
+/// draw them or keep their textures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    pub max_screens: usize,
+    pub max_screens_per_owner: usize,
+    /// total texture memory for all screens in bytes
+    pub texture_budget: usize,
+    pub hide: Hide,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_screens: 10,
+            max_screens_per_owner: 4,
+            texture_budget: 256 * 1024 * 1024,
+            hide: Hide::Newest,
+        }
+    }
+}
+
+struct ScreenInfo<'a> {
+    id: usize,
+    /// as claimed, so synced screens count towards their owner's limit too
+    owner: Option<&'a str>,
+    /// ours or a friend's, these are always shown
+    exempt: bool,
+    created_at: Instant,
+    distance_squared: f32,
+    texture_bytes: usize,
+}
+
+impl Limits {
+    /// Which screens to hide so the rest fit, and why
+    fn choose_hidden(&self, screens: &[ScreenInfo]) -> Vec<(usize, String)> {
+        let mut shown: Vec<&ScreenInfo> = screens.iter().collect();
+        let mut hidden = Vec::new();
+
+        loop {
+            let mut owned_counts: HashMap<String, usize> = HashMap::new();
+            // ours and our friends' don't count, they can't be hidden anyway
+            for screen in shown.iter().filter(|screen| !screen.exempt) {
+                if let Some(owner) = screen.owner {
+                    *owned_counts.entry(owner.to_lowercase()).or_default() += 1;
+                }
+            }
+            let crowded_owner = owned_counts
+                .into_iter()
+                .filter(|(_, count)| *count > self.max_screens_per_owner)
+                .max_by_key(|(_, count)| *count);
+            let texture_bytes: usize = shown.iter().map(|screen| screen.texture_bytes).sum();
+
+            // per owner first so that one player's screens are hidden before others'
+            let (reason, owner) = if let Some((owner, count)) = crowded_owner {
+                (format!("{} has {} screens", owner, count), Some(owner))
+            } else if shown.len() > self.max_screens {
+                (format!("{} screens", shown.len()), None)
+            } else if texture_bytes > self.texture_budget {
+                (
+                    format!(
+                        "over the texture budget of {} MB",
+                        self.texture_budget / 1024 / 1024
+                    ),
+                    None,
+                )
+            } else {
+                return hidden;
+            };
+
+            let candidates = shown.iter().enumerate().filter(|(_, screen)| {
+                !screen.exempt
+                    && owner
+                        .as_ref()
+                        .map(|owner| same_owner(screen.owner, owner))
+                        .unwrap_or(true)
+            });
+
+            let victim = match self.hide {
+                Hide::Newest => candidates
+                    .max_by_key(|(_, screen)| screen.created_at)
+                    .map(|(i, _)| i),
+                Hide::Oldest => candidates
+                    .min_by_key(|(_, screen)| screen.created_at)
+                    .map(|(i, _)| i),
+                Hide::Farthest => candidates
+                    .max_by(|(_, a), (_, b)| {
+                        a.distance_squared
+                            .partial_cmp(&b.distance_squared)
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map(|(i, _)| i),
+            };
+
+            match victim {
+                Some(index) => hidden.push((shown.remove(index).id, reason)),
+                // the rest are ours or our friends'
+                None => return hidden,
+            }
+        }
+    }
+}
+
+fn same_owner(a: Option<&str>, b: &str) -> bool {
+    a.map(|a| a.eq_ignore_ascii_case(b)).unwrap_or(false)
+}
+
+fn get_eye_position() -> Option<Vec3> {
+    host::get()
+        .get_player(ENTITIES_SELF_ID as _)
+        .map(|me| me.eye_position)
+}
+
+fn is_exempt(owner: Option<&str>, username: &str) -> bool {
+    owner
+        .map(|owner| {
+            remove_color(owner).trim().eq_ignore_ascii_case(username)
+                || permissions::is_friend(owner)
+        })
+        .unwrap_or(false)
+}
+
+/// Hides the screens over our limits and shows the rest
+///
+/// Returns the screens that were just hidden and why.
+pub fn apply(entities: &mut HashMap<usize, CefEntity>) -> Vec<(usize, String)> {
+    let eye_position = get_eye_position().unwrap_or(Vec3 {
+        X: 0.0,
+        Y: 0.0,
+        Z: 0.0,
+    });
+    let username = host::get().get_username();
+
+    let hidden = {
+        let screens: Vec<ScreenInfo> = entities
+            .values()
+            .map(|entity| ScreenInfo {
+                id: entity.id,
+                owner: entity.get_owner(),
+                // a synced owner could claim to be us
+                exempt: is_exempt(entity.get_trusted_owner(), &username),
+                created_at: entity.get_created_at(),
+                distance_squared: (eye_position - entity.entity.Position).length_squared(),
+                texture_bytes: entity.get_texture_bytes(),
+            })
+            .collect();
+
+        LIMITS.with(|cell| cell.borrow().choose_hidden(&screens))
+    };
+
+    let mut newly_hidden = Vec::new();
+    for (entity_id, entity) in entities.iter_mut() {
+        let reason = hidden.iter().find(|(id, _)| id == entity_id);
+
+        let limited = reason.is_some();
+        if let Some(reason) = reason {
+            if !entity.is_limited() {
+                newly_hidden.push(reason.clone());
+            }
+        }
+        entity.set_limited(limited);
+    }
+
+    newly_hidden
+}
+
+pub fn initialize() {
+    match load() {
+        Ok(limits) => {
+            LIMITS.with(|cell| *cell.borrow_mut() = limits);
+        }
+
+        Err(e) => {
+            warn!("couldn't load {}: {}", SETTINGS_PATH, e);
+        }
+    }
+}
+
+fn load() -> Result<Limits> {
+    match fs::read_to_string(SETTINGS_PATH) {
+        Ok(data) => Ok(serde_json::from_str(&data)?),
+        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(Limits::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save(limits: &Limits) -> Result<()> {
+    let data = serde_json::to_string_pretty(limits)?;
+    fs::write(SETTINGS_PATH, data)?;
+
+    Ok(())
+}
+
+pub fn get() -> Limits {
+    LIMITS.with(|cell| cell.borrow().clone())
+}
+
+/// Changes the limits and saves them
+pub fn update<F>(f: F) -> Result<()>
+where
+    F: FnOnce(&mut Limits),
+{
+    LIMITS.with(|cell| {
+        let limits = &mut *cell.borrow_mut();
+        f(limits);
+        save(limits)
+    })
+}
+
+#[test]
+fn test_choose_hidden() {
+    const MB: usize = 1024 * 1024;
+
+    let start = Instant::now();
+    let screen = |id: usize, owner: &'static str, age: u64, distance_squared: f32| ScreenInfo {
+        id,
+        owner: Some(owner),
+        exempt: owner == "me",
+        created_at: start + std::time::Duration::from_secs(100 - age),
+        distance_squared,
+        texture_bytes: 16 * MB,
+    };
+    let ids = |hidden: Vec<(usize, String)>| -> Vec<usize> {
+        hidden.into_iter().map(|(id, _reason)| id).collect()
+    };
+
+    let mut screens = vec![
+        screen(0, "a", 30, 5.0),
+        screen(1, "b", 20, 50.0),
+        screen(2, "A", 10, 1.0),
+    ];
+
+    let mut limits = Limits {
+        max_screens: 3,
+        max_screens_per_owner: 2,
+        texture_budget: 256 * MB,
+        hide: Hide::Newest,
+    };
+
+    // fits
+    assert!(limits.choose_hidden(&screens).is_empty());
+
+    // too many
+    screens.push(screen(3, "c", 5, 10.0));
+    assert_eq!(ids(limits.choose_hidden(&screens)), vec![3]);
+
+    limits.hide = Hide::Oldest;
+    assert_eq!(ids(limits.choose_hidden(&screens)), vec![0]);
+
+    limits.hide = Hide::Farthest;
+    assert_eq!(ids(limits.choose_hidden(&screens)), vec![1]);
+
+    // too many for owner a, only theirs are hidden
+    limits.max_screens = 10;
+    limits.max_screens_per_owner = 1;
+    limits.hide = Hide::Newest;
+    assert_eq!(ids(limits.choose_hidden(&screens)), vec![2]);
+
+    // texture budget
+    limits.max_screens_per_owner = 10;
+    limits.texture_budget = 40 * MB;
+    assert_eq!(ids(limits.choose_hidden(&screens)), vec![3, 2]);
+
+    // ours are never hidden, even if that leaves us over
+    limits.texture_budget = 0;
+    screens.push(screen(4, "me", 0, 100.0));
+    assert_eq!(ids(limits.choose_hidden(&screens)), vec![3, 2, 1, 0]);
+}
@@ -0,0 +1,250 @@
+use super::{limits, placement::get_center, EntityManager};
+use crate::{async_manager::AsyncManager, host};
+use classicube_sys::{Vec3, ENTITIES_SELF_ID};
+use futures::{future::RemoteHandle, prelude::*};
+use log::{debug, warn};
+use nalgebra::Vector3;
+use std::{cell::RefCell, collections::HashMap, os::raw::c_int, time::Duration};
+
+// distance and view changes don't need to be noticed instantly
+const STEP_RATE: Duration = Duration::from_millis(250);
+
+// distances are measured in screen sizes, so bigger screens keep detail further away
+const FULL_DISTANCE: f32 = 4.0;
+const REDUCED_DISTANCE: f32 = 8.0;
+const HIDDEN_DISTANCE: f32 = 16.0;
+
+// a screen must move this far past a boundary before its level changes back,
+// so standing on a boundary doesn't keep repainting at a new scale
+const HYSTERESIS: f32 = 0.1;
+
+// half angle of the cone we count as "in view", wider than the real fov
+// so screens at the edges don't drop detail
+const VIEW_ANGLE: f32 = 70.0;
+
+// same as windowless_frame_rate in interface.cc
+const MAX_FRAME_RATE: c_int = 30;
+
+// entity_id, last applied level
+thread_local!(
+    static SCREENS: RefCell<HashMap<usize, Level>> = RefCell::new(HashMap::new());
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Level {
+    Full,
+    Reduced,
+    Low,
+    /// out of view, nothing needs to be drawn but keep the resolution
+    /// so turning around doesn't repaint at a new scale
+    OutOfView,
+    /// far away, the browser stops painting entirely
+    Hidden,
+}
+
+impl Level {
+    fn frame_rate(self) -> c_int {
+        match self {
+            Level::Full => MAX_FRAME_RATE,
+            Level::Reduced => MAX_FRAME_RATE / 2,
+            Level::Low => 5,
+            Level::OutOfView | Level::Hidden => 1,
+        }
+    }
+
+    /// pixels painted per page pixel, None keeps the current scale
+    fn resolution_scale(self) -> Option<f32> {
+        match self {
+            Level::Full => Some(1.0),
+            Level::Reduced => Some(0.5),
+            Level::Low => Some(0.25),
+            Level::OutOfView | Level::Hidden => None,
+        }
+    }
+}
+
+/// Lowers the frame rate and resolution of distant screens and screens
+/// we aren't looking at, and hides browsers that are far away.
+///
+/// Hidden browsers stop painting but their audio keeps playing.
+pub struct Lod {
+    step_loop_handle: Option<RemoteHandle<()>>,
+}
+
+impl Lod {
+    pub fn new() -> Self {
+        Self {
+            step_loop_handle: None,
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        debug!("initialize lod");
+
+        let (f, remote_handle) = async {
+            loop {
+                AsyncManager::sleep(STEP_RATE).await;
+                Self::step();
+            }
+        }
+        .remote_handle();
+
+        AsyncManager::spawn_local_on_main_thread(f);
+        self.step_loop_handle = Some(remote_handle);
+    }
+
+    pub fn shutdown(&mut self) {
+        self.step_loop_handle.take();
+
+        SCREENS.with(|cell| {
+            let screens = &mut *cell.borrow_mut();
+            screens.clear();
+        });
+    }
+
+    fn step() {
+        let maybe_eye = host::get()
+            .get_player(ENTITIES_SELF_ID as _)
+            .map(|me| (me.eye_position, me.head));
+
+        let (eye_position, [pitch, yaw]) = match maybe_eye {
+            Some(eye) => eye,
+            None => return,
+        };
+
+        let eye = Vector3::new(eye_position.X, eye_position.Y, eye_position.Z);
+        let look = Vec3::get_dir_vector(yaw.to_radians(), pitch.to_radians());
+        let look = Vector3::new(look.X, look.Y, look.Z);
+
+        EntityManager::with_all_entities(|entities| {
+            // distances changed, so farthest screens might have too
+            limits::apply(entities);
+
+            SCREENS.with(|cell| {
+                let screens = &mut *cell.borrow_mut();
+
+                // forget removed entities
+                screens.retain(|entity_id, _| entities.contains_key(entity_id));
+
+                for (&entity_id, entity) in entities.iter_mut() {
+                    let browser = match entity.browser.as_ref() {
+                        Some(browser) => browser.clone(),
+                        None => continue,
+                    };
+
+                    let [width, height] = entity.get_size();
+                    let radius = (width * width + height * height).sqrt() / 2.0;
+                    let to_center = get_center(entity) - eye;
+                    let distance = to_center.norm();
+
+                    let in_view = is_in_view(look, to_center, radius);
+                    let relative_distance = distance / width.max(height).max(0.01);
+
+                    let last = screens.get(&entity_id).cloned();
+                    let level = if entity.is_limited() {
+                        Level::Hidden
+                    } else {
+                        choose_level(relative_distance, in_view, last)
+                    };
+                    if last == Some(level) {
+                        continue;
+                    }
+
+                    // new browsers start shown at the full frame rate
+                    let last = last.unwrap_or(Level::Full);
+
+                    if (level == Level::Hidden) != (last == Level::Hidden) {
+                        if let Err(e) = browser.was_hidden(level == Level::Hidden) {
+                            warn!("was_hidden: {}", e);
+                        }
+                    }
+
+                    if let Err(e) = browser.set_frame_rate(level.frame_rate()) {
+                        warn!("set_frame_rate: {}", e);
+                    }
+
+                    if let Some(scale) = level.resolution_scale() {
+                        if (scale - entity.get_resolution_scale()).abs() > std::f32::EPSILON {
+                            if let Err(e) = entity.set_resolution_scale(scale) {
+                                warn!("set_resolution_scale: {}", e);
+                            }
+                        }
+                    }
+
+                    screens.insert(entity_id, level);
+                }
+            });
+        });
+    }
+}
+
+/// if a sphere of `radius` at `to_center` from the eye is inside our view cone
+fn is_in_view(look: Vector3<f32>, to_center: Vector3<f32>, radius: f32) -> bool {
+    let distance = to_center.norm();
+    if distance <= radius {
+        return true;
+    }
+
+    let angle = look.angle(&to_center);
+    let angular_radius = (radius / distance).asin();
+
+    angle <= VIEW_ANGLE.to_radians() + angular_radius
+}
+
+fn level_at(relative_distance: f32, in_view: bool) -> Level {
+    if relative_distance > HIDDEN_DISTANCE {
+        Level::Hidden
+    } else if !in_view {
+        Level::OutOfView
+    } else if relative_distance > REDUCED_DISTANCE {
+        Level::Low
+    } else if relative_distance > FULL_DISTANCE {
+        Level::Reduced
+    } else {
+        Level::Full
+    }
+}
+
+/// Keeps `last` while we're within HYSTERESIS of its boundaries
+fn choose_level(relative_distance: f32, in_view: bool, last: Option<Level>) -> Level {
+    let nearer = level_at(relative_distance * (1.0 - HYSTERESIS), in_view);
+    let farther = level_at(relative_distance * (1.0 + HYSTERESIS), in_view);
+
+    match last {
+        Some(last) if last >= nearer && last <= farther => last,
+        _ => level_at(relative_distance, in_view),
+    }
+}
+
+#[test]
+fn test_choose_level() {
+    assert_eq!(choose_level(1.0, true, None), Level::Full);
+    assert_eq!(choose_level(6.0, true, None), Level::Reduced);
+    assert_eq!(choose_level(12.0, true, None), Level::Low);
+    assert_eq!(choose_level(1.0, false, None), Level::OutOfView);
+    assert_eq!(choose_level(20.0, true, None), Level::Hidden);
+    assert_eq!(choose_level(20.0, false, None), Level::Hidden);
+
+    // just past a boundary keeps the last level
+    assert_eq!(choose_level(4.2, true, Some(Level::Full)), Level::Full);
+    assert_eq!(
+        choose_level(3.8, true, Some(Level::Reduced)),
+        Level::Reduced
+    );
+    assert_eq!(choose_level(5.0, true, Some(Level::Full)), Level::Reduced);
+
+    // turning around always changes level
+    assert_eq!(choose_level(1.0, true, Some(Level::OutOfView)), Level::Full);
+}
+
+#[test]
+fn test_is_in_view() {
+    let look = Vector3::new(0.0, 0.0, -1.0);
+
+    assert!(is_in_view(look, Vector3::new(0.0, 0.0, -10.0), 1.0));
+    assert!(!is_in_view(look, Vector3::new(0.0, 0.0, 10.0), 1.0));
+    // standing inside the screen's bounds
+    assert!(is_in_view(look, Vector3::new(0.0, 0.0, 0.5), 1.0));
+    // off to the side but big enough to poke into view
+    assert!(is_in_view(look, Vector3::new(10.0, 0.0, -1.0), 8.0));
+}
@@ -1,19 +1,41 @@
+mod audio_scheduler;
 mod cef_paint;
 mod context_handler;
+mod edit_mode;
 mod entity;
+mod frame_mailbox;
+mod label;
+pub mod limits;
+mod lod;
 mod model;
+pub mod overlay;
+mod placement;
 mod render_model_detour;
-
-pub use self::{cef_paint::cef_paint_callback, entity::CefEntity};
+mod stats_hud;
+mod transform;
+
+pub use self::{
+    audio_scheduler::AudioScheduler,
+    cef_paint::cef_paint_callback,
+    edit_mode::EditMode,
+    entity::{CefEntity, FaceMode},
+    frame_mailbox::FrameStats,
+    label::Label,
+    lod::Lod,
+    placement::{place_on_wall, snap},
+    stats_hud::StatsHud,
+    transform::{grow, nudge, rotate, Transform},
+};
 use self::{
-    context_handler::ContextHandler, model::CefModel, render_model_detour::RenderModelDetour,
+    context_handler::ContextHandler, entity::check_size, model::CefModel,
+    render_model_detour::RenderModelDetour,
 };
 use crate::{
     async_manager::AsyncManager,
     cef::{Cef, CefEvent, RustRefBrowser},
-    chat::hidden_communication::LightEntity,
+    chat::{hidden_communication::LightEntity, Chat},
     error::*,
-    players::{Player, PlayerTrait},
+    players::{policy, Player, PlayerTrait},
 };
 use classicube_sys::Vec3;
 use futures::{
@@ -28,6 +50,7 @@ use std::{
     os::raw::*,
 };
 
+// largest texture and browser resolution
 pub const TEXTURE_WIDTH: usize = 2048;
 pub const TEXTURE_HEIGHT: usize = 2048;
 
@@ -53,6 +76,9 @@ pub struct EntityManager {
 
     render_model_detour: RenderModelDetour,
     context_handler: ContextHandler,
+    audio_scheduler: AudioScheduler,
+    lod: Lod,
+    edit_mode: EditMode,
 
     cef_event_page_loaded: Option<RemoteHandle<()>>,
     cef_event_title_change: Option<RemoteHandle<()>>,
@@ -66,6 +92,9 @@ impl EntityManager {
             model: None,
             render_model_detour,
             context_handler: ContextHandler::new(),
+            audio_scheduler: AudioScheduler::new(),
+            lod: Lod::new(),
+            edit_mode: EditMode::new(),
             cef_event_page_loaded: None,
             cef_event_title_change: None,
         }
@@ -76,6 +105,11 @@ impl EntityManager {
 
         self.context_handler.initialize();
         self.render_model_detour.initialize();
+        self.audio_scheduler.initialize();
+        self.lod.initialize();
+        self.edit_mode.initialize();
+        policy::initialize();
+        limits::initialize();
         self.model = Some(CefModel::register());
 
         let mut event_listener = Cef::create_event_listener();
@@ -85,7 +119,12 @@ impl EntityManager {
                     let browser_id = browser.get_identifier();
 
                     if let Err(e) = EntityManager::with_by_browser_id(browser_id, |entity| {
-                        entity.player.on_page_loaded(&mut browser);
+                        // the card asking us to accept isn't the player's page,
+                        // its hooks run when the accepted page loads
+                        if entity.get_pending_requester().is_none() {
+                            entity.player.on_page_loaded(&mut browser);
+                        }
+                        AudioScheduler::reset(entity.id);
                         Ok(())
                     }) {
                         warn!("{}", e);
@@ -104,7 +143,10 @@ impl EntityManager {
                     let browser_id = browser.get_identifier();
 
                     if let Err(e) = EntityManager::with_by_browser_id(browser_id, |entity| {
-                        entity.player.on_title_change(&mut browser, title);
+                        if entity.get_pending_requester().is_none() {
+                            entity.set_title(title.clone());
+                            entity.player.on_title_change(&mut browser, title);
+                        }
                         Ok(())
                     }) {
                         warn!("{}", e);
@@ -130,6 +172,10 @@ impl EntityManager {
 
         self.context_handler.shutdown();
         self.render_model_detour.shutdown();
+        self.audio_scheduler.shutdown();
+        self.lod.shutdown();
+        self.edit_mode.shutdown();
+        StatsHud::shutdown();
         self.model.take();
         self.cef_event_page_loaded.take();
 
@@ -150,7 +196,12 @@ impl EntityManager {
                 let entities = &mut *entities.borrow_mut();
 
                 if let Some(entity) = entities.get_mut(&entity_id) {
+                    Cef::set_browser_is_media(&browser, entity.player.is_media());
                     entity.browser = Some(browser);
+
+                    if let Err(e) = entity.resize_browser_to_fit() {
+                        warn!("resize_browser_to_fit: {}", e);
+                    }
                 } else {
                     warn!("couldn't find entity for browser id {}", browser_id);
                 }
@@ -158,8 +209,36 @@ impl EntityManager {
         });
     }
 
+    /// The url to load for `player`, or a card asking us to accept it first
+    ///
+    /// `requester` is the other player who asked for this, None if it was us.
+    fn get_url_to_load(
+        entity: &mut CefEntity,
+        player: &mut Player,
+        requester: Option<String>,
+    ) -> String {
+        match requester {
+            Some(requester) if player.needs_confirmation() => {
+                let url = policy::confirm_url(&player.get_url(), &requester);
+                entity.set_pending_requester(Some(requester));
+                url
+            }
+
+            _ => {
+                entity.set_pending_requester(None);
+                player.on_create(entity.id)
+            }
+        }
+    }
+
     /// returns entity_id
-    pub fn create_entity(input: &str) -> Result<usize> {
+    pub fn create_entity(
+        input: &str,
+        owner: Option<String>,
+        requester: Option<String>,
+    ) -> Result<usize> {
+        let mut player = Player::from_input(input)?;
+
         let entity_id = ENTITY_ID.with(|cell| {
             let mut entity_id = cell.get();
 
@@ -171,16 +250,20 @@ impl EntityManager {
             entity_id
         });
 
-        let mut player = Player::from_input(input)?;
-        let url = player.on_create(entity_id);
-
-        ENTITIES.with(|entities| {
+        let url = ENTITIES.with(|entities| {
             let entities = &mut *entities.borrow_mut();
 
-            let entity = CefEntity::register(entity_id, player);
+            let mut entity = CefEntity::register(entity_id, player.clone());
+            let url = Self::get_url_to_load(&mut entity, &mut player, requester);
+            entity.player = player;
+            entity.set_owner(owner, true);
+
             debug!("entity created {}", entity_id);
             entities.insert(entity_id, entity);
+
+            url
         });
+        Self::apply_limits(true);
 
         AsyncManager::spawn_local_on_main_thread(async move {
             let browser = Cef::create_browser(url).await.unwrap();
@@ -192,7 +275,12 @@ impl EntityManager {
     }
 
     /// returns entity_id
-    pub async fn create_entity_from_light_entity(info: LightEntity) -> Result<usize> {
+    ///
+    /// `sender` is who synced it to us, the owner it claims isn't trusted.
+    pub async fn create_entity_from_light_entity(info: LightEntity, sender: &str) -> Result<usize> {
+        // before registering so a bad size doesn't leave half a screen behind
+        check_size(info.size)?;
+
         let entity_id = ENTITY_ID.with(|cell| {
             let mut entity_id = cell.get();
 
@@ -205,14 +293,21 @@ impl EntityManager {
         });
 
         let mut player = info.player.clone();
-        let url = player.on_create(entity_id);
 
-        ENTITIES.with(|entities| {
+        // synced screens always come from another player
+        let requester = sender.to_string();
+
+        let url = ENTITIES.with(|entities| {
             let entities = &mut *entities.borrow_mut();
 
-            let entity = CefEntity::register(entity_id, player);
+            let mut entity = CefEntity::register(entity_id, player.clone());
+            let url = Self::get_url_to_load(&mut entity, &mut player, Some(requester));
+            entity.player = player;
+
             debug!("entity {} created", entity_id);
             entities.insert(entity_id, entity);
+
+            url
         });
 
         EntityManager::with_by_entity_id(entity_id, |entity| {
@@ -220,9 +315,14 @@ impl EntityManager {
 
             e.Position.set(info.pos[0], info.pos[1], info.pos[2]);
 
-            e.RotX = info.ang[0];
-            e.RotY = info.ang[1];
-            entity.set_scale(info.scale);
+            entity.set_angles(info.ang);
+            entity.set_size(info.size)?;
+            entity.set_opacity(info.opacity);
+            entity.set_tint(info.tint);
+            entity.set_lit(info.lit);
+            entity.set_face_mode(info.face_mode);
+            entity.set_name(info.name);
+            entity.set_owner(info.owner, false);
 
             AsyncManager::spawn_local_on_main_thread(async move {
                 let browser = Cef::create_browser(url).await.unwrap();
@@ -230,21 +330,67 @@ impl EntityManager {
                 EntityManager::attach_browser_to_entity(entity_id, browser);
             });
 
-            Ok(entity_id)
-        })
+            Ok(())
+        })?;
+        Self::apply_limits(true);
+
+        Ok(entity_id)
     }
 
-    pub fn entity_play(input: &str, entity_id: usize) -> Result<()> {
+    pub fn entity_play(input: &str, entity_id: usize, requester: Option<String>) -> Result<()> {
         let mut player = Player::from_input(input)?;
-        let url = player.on_create(entity_id);
 
-        let browser = EntityManager::with_by_entity_id(entity_id, |entity| {
+        let (browser, url) = EntityManager::with_by_entity_id(entity_id, |entity| {
+            let browser = entity.browser.as_ref().chain_err(|| "no browser")?.clone();
+
+            let url = Self::get_url_to_load(entity, &mut player, requester);
+            Cef::set_browser_is_media(&browser, player.is_media());
+            entity.player = player;
+
+            Ok((browser, url))
+        })?;
+
+        browser.load_url(url)?;
+
+        Ok(())
+    }
+
+    /// Loads the url a screen was waiting on us to accept
+    ///
+    /// If `remember`, we won't ask about this host again.
+    pub fn accept_pending(entity_id: usize, remember: bool) -> Result<()> {
+        let (browser, url, host) = EntityManager::with_by_entity_id(entity_id, |entity| {
+            if entity.get_pending_requester().is_none() {
+                bail!("nothing to accept");
+            }
+
+            let browser = entity
+                .browser
+                .as_ref()
+                .chain_err(|| "browser not ready yet")?
+                .clone();
+
+            let host = url::Url::parse(&entity.player.get_url())
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.to_lowercase()));
+
+            let mut player = entity.player.clone();
+            let url = Self::get_url_to_load(entity, &mut player, None);
             entity.player = player;
 
-            let browser = entity.browser.as_ref().chain_err(|| "no browser")?;
-            Ok(browser.clone())
+            Ok((browser, url, host))
         })?;
 
+        if remember {
+            if let Some(host) = host {
+                policy::update(|policy| {
+                    if !policy.accepted.contains(&host) {
+                        policy.accepted.push(host);
+                    }
+                })?;
+            }
+        }
+
         browser.load_url(url)?;
 
         Ok(())
@@ -266,8 +412,35 @@ impl EntityManager {
         })
     }
 
+    /// Hides screens over our limits, they still exist for everyone else
+    ///
+    /// If `notify`, tells us about screens that were just hidden.
+    pub fn apply_limits(notify: bool) {
+        let hidden = ENTITIES.with(|entities| limits::apply(&mut entities.borrow_mut()));
+
+        for (entity_id, reason) in hidden {
+            debug!("hiding entity {}: {}", entity_id, reason);
+
+            if notify {
+                Chat::print(format!(
+                    "cef: not showing screen {}, {}, see \"cef limits\"",
+                    entity_id, reason
+                ));
+            }
+        }
+    }
+
     pub async fn remove_entity(entity_id: usize) -> Result<()> {
-        let maybe_browser = ENTITIES.with(|entities| {
+        if let Some(browser) = Self::take_entity(entity_id)? {
+            Self::close_entity_browser(browser).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the entity, returning its browser which still needs closing
+    fn take_entity(entity_id: usize) -> Result<Option<RustRefBrowser>> {
+        ENTITIES.with(|entities| {
             let entities = &mut *entities.borrow_mut();
 
             if let Some(mut entity) = entities.remove(&entity_id) {
@@ -282,17 +455,17 @@ impl EntityManager {
                     entity_id
                 );
             }
-        })?;
+        })
+    }
 
-        if let Some(browser) = maybe_browser {
-            EntityManager::on_browser_close(&browser);
+    async fn close_entity_browser(browser: RustRefBrowser) -> Result<()> {
+        EntityManager::on_browser_close(&browser);
 
-            debug!(
-                "entity_manager closing browser {}",
-                browser.get_identifier()
-            );
-            Cef::close_browser(&browser).await?;
-        }
+        debug!(
+            "entity_manager closing browser {}",
+            browser.get_identifier()
+        );
+        Cef::close_browser(&browser).await?;
 
         Ok(())
     }
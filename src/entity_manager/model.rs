@@ -1,12 +1,16 @@
-use super::{TEXTURE_HEIGHT, TEXTURE_WIDTH};
+use super::entity::{RENDER_BACK_COL, RENDER_LABEL, RENDER_OUTLINE_COL};
 use crate::helpers::*;
 use classicube_sys::{
     Bitmap, Entity, Model, ModelTex, ModelVertex, Model_Init, Model_Register, OwnedGfxTexture,
-    PackedCol, PackedCol_Make, MODEL_BOX_VERTICES,
+    MODEL_BOX_VERTICES, PACKEDCOL_A_SHIFT, PACKEDCOL_WHITE,
 };
 use std::{ffi::CString, mem, pin::Pin};
 
-const WHITE_TRANSPARENT: PackedCol = PackedCol_Make(255, 255, 255, 0);
+// in model units, the screen is MODEL_WIDTH units wide
+const OUTLINE_THICKNESS: f32 = 0.15;
+
+// entities draw with their own textures, this is only a fallback
+const DEFAULT_TEXTURE_SIZE: usize = 64;
 
 pub struct CefModel {
     name: Pin<Box<CString>>,
@@ -51,12 +55,12 @@ impl CefModel {
 impl CefModel {
     unsafe fn register_gfx_texture(&mut self) {
         // must be a vec or else we try to fit huge array onto stack and crash!
-        let mut pixels: Vec<u8> = vec![255; 4 * TEXTURE_WIDTH * TEXTURE_HEIGHT];
+        let mut pixels: Vec<u8> = vec![255; 4 * DEFAULT_TEXTURE_SIZE * DEFAULT_TEXTURE_SIZE];
 
         let mut bmp = Bitmap {
             Scan0: pixels.as_mut_ptr(),
-            Width: TEXTURE_WIDTH as i32,
-            Height: TEXTURE_HEIGHT as i32,
+            Width: DEFAULT_TEXTURE_SIZE as i32,
+            Height: DEFAULT_TEXTURE_SIZE as i32,
         };
 
         let default_texture = OwnedGfxTexture::create(&mut bmp, true, false);
@@ -83,10 +87,61 @@ impl CefModel {
         let entity = unsafe { &mut *entity };
 
         unsafe {
-            classicube_sys::Gfx_SetAlphaTest(0);
-            classicube_sys::Gfx_SetTexturing(1);
+            // tint, opacity and lighting from CefEntity
+            let col = (*entity.VTABLE).GetCol.unwrap()(entity);
+            let translucent = (col >> PACKEDCOL_A_SHIFT) & 0xFF != 0xFF;
 
-            Texture_RenderShaded(&mut entity.NameTex, WHITE_TRANSPARENT);
+            classicube_sys::Gfx_SetAlphaTest(0);
+            if translucent {
+                classicube_sys::Gfx_SetAlphaBlending(1);
+            }
+
+            if let Some(back_col) = RENDER_BACK_COL.with(|cell| cell.get()) {
+                let tex = &entity.NameTex;
+
+                classicube_sys::Gfx_SetTexturing(0);
+                Gfx_Draw2DFlat(
+                    tex.X as _,
+                    tex.Y as _,
+                    tex.Width as _,
+                    tex.Height as _,
+                    back_col,
+                );
+            } else {
+                classicube_sys::Gfx_SetTexturing(1);
+                Texture_RenderShaded(&mut entity.NameTex, col);
+            }
+
+            if let Some(label) = RENDER_LABEL.with(|cell| cell.get()) {
+                classicube_sys::Gfx_SetTexturing(1);
+                classicube_sys::Gfx_BindTexture(label.texture.ID);
+                Gfx_Draw2DTextureF(
+                    label.x,
+                    label.y,
+                    label.width,
+                    label.height,
+                    label.texture.uv,
+                    PACKEDCOL_WHITE,
+                );
+            }
+
+            if let Some(outline_col) = RENDER_OUTLINE_COL.with(|cell| cell.get()) {
+                let tex = &entity.NameTex;
+
+                classicube_sys::Gfx_SetTexturing(0);
+                Gfx_Draw2DOutline(
+                    tex.X as _,
+                    tex.Y as _,
+                    tex.Width as _,
+                    tex.Height as _,
+                    OUTLINE_THICKNESS,
+                    outline_col,
+                );
+            }
+
+            if translucent {
+                classicube_sys::Gfx_SetAlphaBlending(0);
+            }
         }
     }
 
@@ -0,0 +1,101 @@
+use classicube_sys::{
+    GuiPriority_GUI_PRIORITY_HUD, Gui_Add, Gui_Remove, Screen, ScreenVTABLE, String as CcString,
+};
+use std::{
+    mem,
+    os::raw::{c_char, c_double, c_float, c_int, c_void},
+    pin::Pin,
+};
+
+// just above the hud, so we see keys and scrolling before the hotbar and
+// keybinds do, and menus and chat still come before us
+const DEFAULT_PRIORITY: c_int = GuiPriority_GUI_PRIORITY_HUD as c_int + 1;
+
+/// A game gui screen that draws over the world or takes input
+///
+/// Its `v_table` decides what it does, fill the rest with the no-op
+/// functions below.
+pub struct Overlay {
+    screen: Pin<Box<Screen>>,
+    priority: c_int,
+    added: bool,
+}
+
+impl Overlay {
+    pub fn new(v_table: &'static ScreenVTABLE) -> Self {
+        Self::with_priority(v_table, DEFAULT_PRIORITY)
+    }
+
+    /// Higher priorities get input first
+    pub fn with_priority(v_table: &'static ScreenVTABLE, priority: c_int) -> Self {
+        let mut screen: Pin<Box<Screen>> = Box::pin(unsafe { mem::zeroed() });
+        screen.VTABLE = v_table;
+
+        Self {
+            screen,
+            priority,
+            added: false,
+        }
+    }
+
+    pub fn add(&mut self) {
+        if self.added {
+            return;
+        }
+
+        unsafe {
+            Gui_Add(self.screen.as_mut().get_unchecked_mut(), self.priority);
+        }
+        self.added = true;
+    }
+
+    pub fn remove(&mut self) {
+        if !self.added {
+            return;
+        }
+
+        unsafe {
+            Gui_Remove(self.screen.as_mut().get_unchecked_mut());
+        }
+        self.added = false;
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+pub unsafe extern "C" fn no_op(_elem: *mut c_void) {}
+
+pub unsafe extern "C" fn no_op_delta(_elem: *mut c_void, _delta: c_double) {}
+
+pub unsafe extern "C" fn ignore_key(_elem: *mut c_void, _key: c_int) -> c_int {
+    0
+}
+
+pub unsafe extern "C" fn no_op_key(_elem: *mut c_void, _key: c_int) {}
+
+pub unsafe extern "C" fn ignore_char(_elem: *mut c_void, _key_char: c_char) -> c_int {
+    0
+}
+
+pub unsafe extern "C" fn ignore_text(_elem: *mut c_void, _text: *const CcString) -> c_int {
+    0
+}
+
+pub unsafe extern "C" fn ignore_pointer(
+    _elem: *mut c_void,
+    _id: c_int,
+    _x: c_int,
+    _y: c_int,
+) -> c_int {
+    0
+}
+
+pub unsafe extern "C" fn no_op_pointer(_elem: *mut c_void, _id: c_int, _x: c_int, _y: c_int) {}
+
+pub unsafe extern "C" fn ignore_scroll(_elem: *mut c_void, _delta: c_float) -> c_int {
+    0
+}
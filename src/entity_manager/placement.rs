@@ -0,0 +1,301 @@
+use super::CefEntity;
+use crate::error::*;
+use classicube_sys::{
+    Blocks, CollideType_COLLIDE_ICE, CollideType_COLLIDE_SLIPPERY_ICE, CollideType_COLLIDE_SOLID,
+    DrawType_DRAW_GAS, DrawType_DRAW_SPRITE, Vec3, World,
+};
+use nalgebra::Vector3;
+
+// how far `cef place wall` can reach
+const MAX_PLACE_DISTANCE: f32 = 64.0;
+
+// keep screens slightly off of walls so they don't z-fight
+const WALL_OFFSET: f32 = 0.01;
+
+// screen centers snap to half blocks so they can sit on block faces
+const SNAP_POSITION: f32 = 0.5;
+const SNAP_ANGLE: f32 = 90.0;
+
+fn is_solid_block(x: i32, y: i32, z: i32) -> bool {
+    unsafe {
+        if x < 0 || y < 0 || z < 0 || x >= World.Width || y >= World.Height || z >= World.Length {
+            return false;
+        }
+
+        if World.Blocks.is_null() {
+            return false;
+        }
+
+        let index = (y * World.Length + z) * World.Width + x;
+        let block = *World.Blocks.offset(index as isize) as usize;
+
+        is_wall(
+            Blocks.Collide[block],
+            Blocks.Draw[block],
+            Blocks.MinBB[block],
+            Blocks.MaxBB[block],
+        )
+    }
+}
+
+/// Whether a block with these definitions is something we can put a screen on
+///
+/// Water, plants and thin blocks like panes and slabs don't count.
+fn is_wall(collide: u8, draw: u8, min: Vec3, max: Vec3) -> bool {
+    let collide = collide as u32;
+    let draw = draw as u32;
+
+    let solid = collide == CollideType_COLLIDE_SOLID
+        || collide == CollideType_COLLIDE_ICE
+        || collide == CollideType_COLLIDE_SLIPPERY_ICE;
+    let visible = draw != DrawType_DRAW_GAS && draw != DrawType_DRAW_SPRITE;
+    let full = min.X <= 0.0
+        && min.Y <= 0.0
+        && min.Z <= 0.0
+        && max.X >= 1.0
+        && max.Y >= 1.0
+        && max.Z >= 1.0;
+
+    solid && visible && full
+}
+
+/// Walks the blocks along a ray until `is_solid` returns true
+///
+/// Returns the block's position and the normal of the face we hit.
+fn raycast_block<F>(
+    origin: Vector3<f32>,
+    dir: Vector3<f32>,
+    max_distance: f32,
+    is_solid: F,
+) -> Option<([i32; 3], [i32; 3])>
+where
+    F: Fn(i32, i32, i32) -> bool,
+{
+    let dir = dir.normalize();
+
+    let mut block = [
+        origin.x.floor() as i32,
+        origin.y.floor() as i32,
+        origin.z.floor() as i32,
+    ];
+    let mut step = [0; 3];
+    // distance along the ray to the next block boundary on each axis
+    let mut t_max = [std::f32::INFINITY; 3];
+    // distance along the ray to cross a whole block on each axis
+    let mut t_delta = [std::f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        if dir[axis] > 0.0 {
+            step[axis] = 1;
+            t_max[axis] = (block[axis] as f32 + 1.0 - origin[axis]) / dir[axis];
+            t_delta[axis] = 1.0 / dir[axis];
+        } else if dir[axis] < 0.0 {
+            step[axis] = -1;
+            t_max[axis] = (block[axis] as f32 - origin[axis]) / dir[axis];
+            t_delta[axis] = -1.0 / dir[axis];
+        }
+    }
+
+    loop {
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] {
+                0
+            } else {
+                2
+            }
+        } else if t_max[1] < t_max[2] {
+            1
+        } else {
+            2
+        };
+
+        if t_max[axis] > max_distance {
+            return None;
+        }
+
+        block[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        if is_solid(block[0], block[1], block[2]) {
+            let mut normal = [0; 3];
+            normal[axis] = -step[axis];
+
+            return Some((block, normal));
+        }
+    }
+}
+
+/// pitch, yaw for the front of a screen to face `normal`
+fn angles_for_normal([x, y, z]: [i32; 3], player_yaw: f32) -> [f32; 2] {
+    let player_yaw = snap_angle(player_yaw);
+
+    match (x, y, z) {
+        (1, _, _) => [0.0, 90.0],
+        (-1, _, _) => [0.0, 270.0],
+        (_, _, 1) => [0.0, 180.0],
+        (_, _, -1) => [0.0, 0.0],
+        // on a floor, the top of the screen points away from the player
+        (_, 1, _) => [270.0, snap_angle(player_yaw + 180.0)],
+        // on a ceiling, the top of the screen points towards the player
+        _ => [90.0, player_yaw],
+    }
+}
+
+fn snap_angle(angle: f32) -> f32 {
+    ((angle / SNAP_ANGLE).round() * SNAP_ANGLE).rem_euclid(360.0)
+}
+
+fn snap_position(v: f32) -> f32 {
+    (v / SNAP_POSITION).round() * SNAP_POSITION
+}
+
+/// Moves the screen so that its center is at `center`
+pub(super) fn set_center(entity: &mut CefEntity, center: Vector3<f32>) {
+    let [_, height] = entity.get_size();
+    let up = entity.get_rotation().transform_vector(&Vector3::y());
+
+    // our position is the bottom center of the screen
+    let position = center - up * (height / 2.0);
+    entity
+        .entity
+        .Position
+        .set(position.x, position.y, position.z);
+}
+
+pub(super) fn get_center(entity: &CefEntity) -> Vector3<f32> {
+    let [_, height] = entity.get_size();
+    let up = entity.get_rotation().transform_vector(&Vector3::y());
+
+    let pos = entity.entity.Position;
+    Vector3::new(pos.X, pos.Y, pos.Z) + up * (height / 2.0)
+}
+
+/// Puts the screen flush against the block face the player is looking at
+pub fn place_on_wall(
+    entity: &mut CefEntity,
+    eye_position: Vec3,
+    yaw: f32,
+    pitch: f32,
+) -> Result<()> {
+    let dir = Vec3::get_dir_vector(yaw.to_radians(), pitch.to_radians());
+
+    let (block, normal) = raycast_block(
+        Vector3::new(eye_position.X, eye_position.Y, eye_position.Z),
+        Vector3::new(dir.X, dir.Y, dir.Z),
+        MAX_PLACE_DISTANCE,
+        is_solid_block,
+    )
+    .chain_err(|| "not looking at a block")?;
+
+    let [pitch, yaw] = angles_for_normal(normal, yaw);
+    entity.set_angles([pitch, yaw, 0.0]);
+
+    let normal = Vector3::new(normal[0] as f32, normal[1] as f32, normal[2] as f32);
+    let block_center = Vector3::new(
+        block[0] as f32 + 0.5,
+        block[1] as f32 + 0.5,
+        block[2] as f32 + 0.5,
+    );
+    let face_center = block_center + normal * (0.5 + WALL_OFFSET);
+
+    set_center(entity, face_center);
+
+    Ok(())
+}
+
+/// Rounds the screen's angles to 90 degrees and its center to half blocks
+pub fn snap(entity: &mut CefEntity) {
+    let [pitch, yaw, roll] = entity.get_angles();
+    entity.set_angles([snap_angle(pitch), snap_angle(yaw), snap_angle(roll)]);
+
+    let center = get_center(entity);
+    let center = Vector3::new(
+        snap_position(center.x),
+        snap_position(center.y),
+        snap_position(center.z),
+    );
+
+    // move off of any block face we landed on
+    let center = center + entity.get_normal() * WALL_OFFSET;
+
+    set_center(entity, center);
+}
+
+#[test]
+fn test_raycast_block() {
+    // a wall at x = 5
+    let is_solid = |x: i32, _y: i32, _z: i32| x == 5;
+
+    let hit = raycast_block(
+        Vector3::new(0.5, 0.5, 0.5),
+        Vector3::new(1.0, 0.0, 0.0),
+        64.0,
+        is_solid,
+    );
+    assert_eq!(hit, Some(([5, 0, 0], [-1, 0, 0])));
+
+    // looking away from the wall
+    let hit = raycast_block(
+        Vector3::new(0.5, 0.5, 0.5),
+        Vector3::new(-1.0, 0.0, 0.0),
+        64.0,
+        is_solid,
+    );
+    assert_eq!(hit, None);
+
+    // too far away
+    let hit = raycast_block(
+        Vector3::new(0.5, 0.5, 0.5),
+        Vector3::new(1.0, 0.0, 0.0),
+        2.0,
+        is_solid,
+    );
+    assert_eq!(hit, None);
+}
+
+#[test]
+fn test_is_wall() {
+    let zero = Vec3 {
+        X: 0.0,
+        Y: 0.0,
+        Z: 0.0,
+    };
+    let one = Vec3 {
+        X: 1.0,
+        Y: 1.0,
+        Z: 1.0,
+    };
+    let pane = Vec3 {
+        X: 1.0,
+        Y: 1.0,
+        Z: 0.5625,
+    };
+    let solid = CollideType_COLLIDE_SOLID as u8;
+
+    assert!(is_wall(solid, 0, zero, one));
+    // glass
+    assert!(is_wall(solid, 1, zero, one));
+    // water
+    assert!(!is_wall(
+        classicube_sys::CollideType_COLLIDE_LIQUID_WATER as u8,
+        3,
+        zero,
+        one
+    ));
+    // flowers
+    assert!(!is_wall(
+        classicube_sys::CollideType_COLLIDE_GAS as u8,
+        DrawType_DRAW_SPRITE as u8,
+        zero,
+        one
+    ));
+    assert!(!is_wall(solid, 1, zero, pane));
+}
+
+#[test]
+fn test_snap_angle() {
+    assert_eq!(snap_angle(44.0), 0.0);
+    assert_eq!(snap_angle(46.0), 90.0);
+    assert_eq!(snap_angle(-80.0), 270.0);
+    assert_eq!(snap_angle(350.0), 0.0);
+}
@@ -1,4 +1,5 @@
-use super::ENTITIES;
+use super::{EditMode, EntityManager, ENTITIES};
+use crate::host;
 use classicube_helpers::detour::static_detour;
 use classicube_sys::*;
 use std::os::raw::{c_double, c_float};
@@ -16,13 +17,26 @@ fn hook(local_player_entity: *mut Entity, delta: c_double, t: c_float) {
         DETOUR.call(local_player_entity, delta, t);
     }
 
-    ENTITIES.with(|entities| {
-        let entities = &mut *entities.borrow_mut();
+    let maybe_eye = host::get()
+        .get_player(ENTITIES_SELF_ID as _)
+        .map(|me| (me.eye_position, me.head));
 
-        for entity in entities.values_mut() {
-            entity.render_model();
-        }
-    });
+    if let Some((eye_position, head)) = maybe_eye {
+        EditMode::update(eye_position, head);
+
+        ENTITIES.with(|entities| {
+            let entities = &mut *entities.borrow_mut();
+
+            // the screen that cef commands would affect
+            let target_id = EntityManager::get_closest_mut(eye_position, entities).map(|e| e.id);
+
+            for entity in entities.values_mut() {
+                // at most one upload per screen per game frame
+                entity.upload_frame();
+                entity.render_model(eye_position, Some(entity.id) == target_id);
+            }
+        });
+    }
 }
 
 pub struct RenderModelDetour {}
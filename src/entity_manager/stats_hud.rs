@@ -0,0 +1,160 @@
+use super::{
+    label::Label,
+    overlay::{self, Overlay},
+};
+use crate::{helpers::*, metrics};
+use classicube_sys::{
+    Gfx_BindTexture, Gfx_SetTexturing, PackedCol_Make, ScreenVTABLE, PACKEDCOL_WHITE,
+};
+use std::{
+    cell::{Cell, RefCell},
+    os::raw::{c_double, c_void},
+    time::{Duration, Instant},
+};
+
+// remaking text textures every frame would be slow
+const REFRESH_RATE: Duration = Duration::from_secs(1);
+
+// pixels from the top left of the window
+const MARGIN: f32 = 8.0;
+
+thread_local!(
+    static ENABLED: Cell<bool> = Cell::new(false);
+);
+
+thread_local!(
+    static HUD: RefCell<Option<Hud>> = RefCell::new(None);
+);
+
+// drawn with the game's gui, after the world
+thread_local!(
+    static OVERLAY: RefCell<Option<Overlay>> = RefCell::new(None);
+);
+
+static V_TABLE: ScreenVTABLE = ScreenVTABLE {
+    Init: Some(overlay::no_op),
+    Update: Some(overlay::no_op_delta),
+    Free: Some(overlay::no_op),
+    Render: Some(render),
+    BuildMesh: Some(overlay::no_op),
+    HandlesInputDown: Some(overlay::ignore_key),
+    OnInputUp: Some(overlay::no_op_key),
+    HandlesKeyPress: Some(overlay::ignore_char),
+    HandlesTextChanged: Some(overlay::ignore_text),
+    HandlesPointerDown: Some(overlay::ignore_pointer),
+    OnPointerUp: Some(overlay::no_op_pointer),
+    HandlesPointerMove: Some(overlay::ignore_pointer),
+    HandlesMouseScroll: Some(overlay::ignore_scroll),
+    Layout: Some(overlay::no_op),
+    ContextLost: Some(overlay::no_op),
+    ContextRecreated: Some(overlay::no_op),
+};
+
+unsafe extern "C" fn render(_elem: *mut c_void, _delta: c_double) {
+    StatsHud::render();
+}
+
+struct Hud {
+    lines: Vec<Label>,
+    updated_at: Instant,
+}
+
+/// Draws the `cef stats` summary in the corner of the screen
+pub struct StatsHud {}
+
+impl StatsHud {
+    pub fn set_enabled(enabled: bool) {
+        ENABLED.with(|cell| cell.set(enabled));
+
+        OVERLAY.with(|cell| {
+            let overlay = &mut *cell.borrow_mut();
+
+            if enabled {
+                overlay.get_or_insert_with(|| Overlay::new(&V_TABLE)).add();
+            } else {
+                overlay.take();
+            }
+        });
+
+        if !enabled {
+            Self::clear();
+        }
+    }
+
+    pub fn shutdown() {
+        Self::set_enabled(false);
+    }
+
+    pub fn is_enabled() -> bool {
+        ENABLED.with(|cell| cell.get())
+    }
+
+    /// forget our text textures, they get remade on the next render
+    pub fn clear() {
+        HUD.with(|cell| {
+            cell.borrow_mut().take();
+        });
+    }
+
+    /// Called by the game's gui, which is already drawing in 2D
+    fn render() {
+        if !Self::is_enabled() {
+            return;
+        }
+
+        HUD.with(|cell| {
+            let hud = &mut *cell.borrow_mut();
+
+            let stale = hud
+                .as_ref()
+                .map(|hud| hud.updated_at.elapsed() >= REFRESH_RATE)
+                .unwrap_or(true);
+            if stale {
+                *hud = Some(Hud {
+                    lines: metrics::get_summary().into_iter().map(Label::new).collect(),
+                    updated_at: Instant::now(),
+                });
+            }
+
+            let lines = match hud {
+                Some(hud) => &hud.lines,
+                None => return,
+            };
+
+            let width = lines
+                .iter()
+                .map(|label| label.texture.Width as f32)
+                .fold(0.0, f32::max);
+            let height: f32 = lines.iter().map(|label| label.texture.Height as f32).sum();
+
+            unsafe {
+                Gfx_SetTexturing(0);
+                Gfx_Draw2DFlat(
+                    (MARGIN / 2.0) as _,
+                    (MARGIN / 2.0) as _,
+                    (width + MARGIN) as _,
+                    (height + MARGIN) as _,
+                    PackedCol_Make(0, 0, 0, 160),
+                );
+
+                Gfx_SetTexturing(1);
+                let mut y = MARGIN;
+                for label in lines {
+                    let texture = &label.texture;
+                    Gfx_BindTexture(texture.ID);
+                    Gfx_Draw2DTextureF(
+                        MARGIN,
+                        y,
+                        texture.Width as f32,
+                        texture.Height as f32,
+                        texture.uv,
+                        PACKEDCOL_WHITE,
+                    );
+                    y += texture.Height as f32;
+                }
+
+                Gfx_SetTexturing(0);
+            }
+        });
+    }
+}
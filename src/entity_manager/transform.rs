@@ -0,0 +1,223 @@
+use super::{
+    placement::{get_center, set_center},
+    CefEntity,
+};
+use crate::error::*;
+use nalgebra::{UnitQuaternion, Vector3};
+use std::collections::VecDeque;
+
+// how many transforms each screen remembers for undo
+const MAX_HISTORY: usize = 50;
+
+/// Everything about a screen's placement that can be undone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: [f32; 3],
+    /// pitch, yaw, roll
+    pub angles: [f32; 3],
+    /// width, height in blocks
+    pub size: [f32; 2],
+}
+
+impl Transform {
+    pub fn from_entity(entity: &CefEntity) -> Self {
+        let pos = entity.entity.Position;
+
+        Self {
+            position: [pos.X, pos.Y, pos.Z],
+            angles: entity.get_angles(),
+            size: entity.get_size(),
+        }
+    }
+
+    pub fn apply(self, entity: &mut CefEntity) -> Result<()> {
+        // first so a bad size changes nothing
+        if entity.get_size() != self.size {
+            entity.set_size(self.size)?;
+        }
+
+        let [x, y, z] = self.position;
+        entity.entity.Position.set(x, y, z);
+
+        entity.set_angles(self.angles);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TransformHistory {
+    undo: VecDeque<Transform>,
+    redo: Vec<Transform>,
+}
+
+impl TransformHistory {
+    /// Remember `transform` before it gets changed
+    pub fn push(&mut self, transform: Transform) {
+        if self.undo.back() == Some(&transform) {
+            return;
+        }
+
+        self.undo.push_back(transform);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.pop_front();
+        }
+
+        self.redo.clear();
+    }
+
+    /// Returns the transform to go back to
+    pub fn undo(&mut self, current: Transform) -> Option<Transform> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+
+        Some(previous)
+    }
+
+    /// Returns the transform to go forward to
+    pub fn redo(&mut self, current: Transform) -> Option<Transform> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+
+        Some(next)
+    }
+}
+
+/// Moves the screen along `local_direction` in the screen's own frame
+///
+/// -z is forward (out of the front), -x is right and +y is up.
+pub fn nudge(entity: &mut CefEntity, local_direction: Vector3<f32>, amount: f32) {
+    let offset = entity.get_rotation().transform_vector(&local_direction) * amount;
+
+    let pos = &mut entity.entity.Position;
+    pos.set(pos.X + offset.x, pos.Y + offset.y, pos.Z + offset.z);
+}
+
+/// Turns `[pitch, yaw, roll]` degrees around the screen's own axes,
+/// keeping its center in place
+pub fn rotate(entity: &mut CefEntity, angles: [f32; 3]) {
+    let center = get_center(entity);
+
+    let rotation = entity.get_rotation() * rotation_from_angles(angles);
+    entity.set_rotation(rotation);
+
+    set_center(entity, center);
+}
+
+/// Rotation for `[pitch, yaw, roll]` degrees
+///
+/// Same order as the game's entity transform: roll, then pitch, then yaw.
+pub fn rotation_from_angles([pitch, yaw, roll]: [f32; 3]) -> UnitQuaternion<f32> {
+    // positive pitch is clockwise on the -x axis
+    // positive yaw is clockwise on the -y axis
+    // positive roll is clockwise on the -z axis
+    UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -yaw.to_radians())
+        * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -pitch.to_radians())
+        * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), -roll.to_radians())
+}
+
+/// `[pitch, yaw, roll]` degrees from 0 to 360 for `rotation`
+pub fn angles_from_rotation(rotation: UnitQuaternion<f32>) -> [f32; 3] {
+    // rotation_from_angles is Ry(-yaw) * Rx(-pitch) * Rz(-roll)
+    let m = rotation.to_rotation_matrix().into_inner();
+
+    let sin_pitch = (-m[(1, 2)]).max(-1.0).min(1.0);
+    let neg_pitch = sin_pitch.asin();
+
+    let (neg_yaw, neg_roll) = if sin_pitch.abs() < 0.9999 {
+        (m[(0, 2)].atan2(m[(2, 2)]), m[(1, 0)].atan2(m[(1, 1)]))
+    } else {
+        // facing straight up or down, yaw and roll turn the same way
+        ((-m[(2, 0)]).atan2(m[(0, 0)]), 0.0)
+    };
+
+    [
+        (-neg_pitch.to_degrees()).rem_euclid(360.0),
+        (-neg_yaw.to_degrees()).rem_euclid(360.0),
+        (-neg_roll.to_degrees()).rem_euclid(360.0),
+    ]
+}
+
+/// Multiplies the size by `factor`, keeping the screen's center in place
+pub fn grow(entity: &mut CefEntity, factor: f32) -> Result<()> {
+    let center = get_center(entity);
+
+    let [width, height] = entity.get_size();
+    entity.set_size([width * factor, height * factor])?;
+
+    set_center(entity, center);
+
+    Ok(())
+}
+
+#[test]
+fn test_rotation() {
+    fn assert_same_rotation(a: UnitQuaternion<f32>, b: UnitQuaternion<f32>) {
+        assert!(a.angle_to(&b) < 0.001, "{:?} != {:?}", a, b);
+    }
+
+    for &angles in &[
+        [0.0, 0.0, 0.0],
+        [10.0, 20.0, 30.0],
+        [350.0, 90.0, 45.0],
+        [90.0, 45.0, 0.0],
+    ] {
+        let rotation = rotation_from_angles(angles);
+        assert_same_rotation(
+            rotation_from_angles(angles_from_rotation(rotation)),
+            rotation,
+        );
+    }
+
+    // with no roll, turning pitch is the same as adding to it
+    let turned = rotation_from_angles([0.0, 30.0, 0.0]) * rotation_from_angles([10.0, 0.0, 0.0]);
+    let angles = angles_from_rotation(turned);
+    assert!((angles[0] - 10.0).abs() < 0.01);
+    assert!((angles[1] - 30.0).abs() < 0.01);
+
+    // rolled 90, the screen's own pitch axis is the world's yaw axis
+    let rolled = rotation_from_angles([0.0, 0.0, 90.0]);
+    let turned = rolled * rotation_from_angles([20.0, 0.0, 0.0]);
+    let expected =
+        UnitQuaternion::from_axis_angle(&(rolled * Vector3::x_axis()), -20f32.to_radians())
+            * rolled;
+    assert_same_rotation(turned, expected);
+    // adding the angles would turn around the world's x axis
+    assert!(turned.angle_to(&rotation_from_angles([20.0, 0.0, 90.0])) > 0.1);
+}
+
+#[test]
+fn test_transform_history() {
+    fn at(x: f32) -> Transform {
+        Transform {
+            position: [x, 0.0, 0.0],
+            angles: [0.0, 0.0, 0.0],
+            size: [4.0, 2.25],
+        }
+    }
+
+    let mut history = TransformHistory::default();
+    assert_eq!(history.undo(at(0.0)), None);
+
+    // moved 0 -> 1 -> 2
+    history.push(at(0.0));
+    history.push(at(1.0));
+
+    assert_eq!(history.undo(at(2.0)), Some(at(1.0)));
+    assert_eq!(history.undo(at(1.0)), Some(at(0.0)));
+    assert_eq!(history.undo(at(0.0)), None);
+
+    assert_eq!(history.redo(at(0.0)), Some(at(1.0)));
+    assert_eq!(history.redo(at(1.0)), Some(at(2.0)));
+    assert_eq!(history.redo(at(2.0)), None);
+
+    // a new change forgets the redo stack
+    assert_eq!(history.undo(at(2.0)), Some(at(1.0)));
+    history.push(at(1.0));
+    assert_eq!(history.redo(at(5.0)), None);
+
+    for i in 0..(MAX_HISTORY * 2) {
+        history.push(at(i as f32));
+    }
+    assert_eq!(history.undo.len(), MAX_HISTORY);
+}
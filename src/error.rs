@@ -4,12 +4,15 @@ use error_chain::error_chain;
 error_chain! {
     foreign_links {
         Fmt(::std::fmt::Error);
+        Io(::std::io::Error);
         ParseFloatError(::std::num::ParseFloatError);
         ParseIntError(::std::num::ParseIntError);
         Url(url::ParseError);
         Tokio(tokio::task::JoinError);
         Bincode(bincode::Error);
         Base64(base64::DecodeError);
+        Json(serde_json::Error);
+        Png(png::EncodingError);
     }
 
     links {
@@ -21,5 +24,10 @@ error_chain! {
             description("cef error")
             display("cef error {}", return_value)
         }
+
+        JavascriptError(message: String) {
+            description("javascript error")
+            display("javascript error: {}", message)
+        }
     }
 }
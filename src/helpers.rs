@@ -1,7 +1,7 @@
 #![allow(non_snake_case)]
 
 use classicube_sys::*;
-use std::cell::RefCell;
+use std::{cell::RefCell, os::raw::c_int};
 
 // Gfx_quadVb = Gfx_CreateDynamicVb(VERTEX_FORMAT_P3FC4B, 4);
 // Gfx_texVb  = Gfx_CreateDynamicVb(VERTEX_FORMAT_P3FT2FC4B, 4);
@@ -14,41 +14,98 @@ thread_local!(
     pub static TEX_VB: RefCell<Option<OwnedGfxVertexBuffer>> = RefCell::new(None);
 );
 
-// pub unsafe fn Gfx_Draw2DFlat(x: c_int, y: c_int, width: c_int, height: c_int, col: PackedCol) {
-//     let mut verts = [
-//         VertexP3fC4b {
-//             X: x as _,
-//             Y: y as _,
-//             Z: 0 as _,
-//             Col: col as _,
-//         },
-//         VertexP3fC4b {
-//             X: (x + width) as _,
-//             Y: y as _,
-//             Z: 0 as _,
-//             Col: col as _,
-//         },
-//         VertexP3fC4b {
-//             X: (x + width) as _,
-//             Y: (y + height) as _,
-//             Z: 0 as _,
-//             Col: col as _,
-//         },
-//         VertexP3fC4b {
-//             X: x as _,
-//             Y: (y + height) as _,
-//             Z: 0 as _,
-//             Col: col as _,
-//         },
-//     ];
-
-//     Gfx_SetVertexFormat(VertexFormat__VERTEX_FORMAT_P3FC4B);
-//     QUAD_VB.with(|quad_vb| {
-//         let quad_vb = quad_vb.borrow_mut();
-//         let quad_vb = quad_vb.as_ref().unwrap();
-//         Gfx_UpdateDynamicVb_IndexedTris(quad_vb.resource_id, verts.as_mut_ptr() as _, 4);
-//     });
-// }
+pub unsafe fn Gfx_Draw2DFlat(x: c_int, y: c_int, width: c_int, height: c_int, col: PackedCol) {
+    let mut verts = [
+        VertexP3fC4b {
+            X: x as _,
+            Y: y as _,
+            Z: 0 as _,
+            Col: col as _,
+        },
+        VertexP3fC4b {
+            X: (x + width) as _,
+            Y: y as _,
+            Z: 0 as _,
+            Col: col as _,
+        },
+        VertexP3fC4b {
+            X: (x + width) as _,
+            Y: (y + height) as _,
+            Z: 0 as _,
+            Col: col as _,
+        },
+        VertexP3fC4b {
+            X: x as _,
+            Y: (y + height) as _,
+            Z: 0 as _,
+            Col: col as _,
+        },
+    ];
+
+    Gfx_SetVertexFormat(VertexFormat__VERTEX_FORMAT_P3FC4B);
+    QUAD_VB.with(|quad_vb| {
+        let quad_vb = quad_vb.borrow_mut();
+        let quad_vb = quad_vb.as_ref().unwrap();
+        Gfx_UpdateDynamicVb_IndexedTris(quad_vb.resource_id, verts.as_mut_ptr() as _, 4);
+    });
+}
+
+/// Draws a rectangle outline around `x, y, width, height` extending outwards
+pub unsafe fn Gfx_Draw2DOutline(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    thickness: f32,
+    col: PackedCol,
+) {
+    let (left, top, right, bottom) = (x, y, x + width, y + height);
+    let t = thickness;
+
+    // top, bottom, left, right
+    let sides = [
+        (left - t, top - t, right + t, top),
+        (left - t, bottom, right + t, bottom + t),
+        (left - t, top, left, bottom),
+        (right, top, right + t, bottom),
+    ];
+
+    Gfx_SetVertexFormat(VertexFormat__VERTEX_FORMAT_P3FC4B);
+    for &(x1, y1, x2, y2) in &sides {
+        let mut verts = [
+            VertexP3fC4b {
+                X: x1,
+                Y: y1,
+                Z: 0.0,
+                Col: col,
+            },
+            VertexP3fC4b {
+                X: x2,
+                Y: y1,
+                Z: 0.0,
+                Col: col,
+            },
+            VertexP3fC4b {
+                X: x2,
+                Y: y2,
+                Z: 0.0,
+                Col: col,
+            },
+            VertexP3fC4b {
+                X: x1,
+                Y: y2,
+                Z: 0.0,
+                Col: col,
+            },
+        ];
+
+        QUAD_VB.with(|quad_vb| {
+            let quad_vb = quad_vb.borrow_mut();
+            let quad_vb = quad_vb.as_ref().unwrap();
+            Gfx_UpdateDynamicVb_IndexedTris(quad_vb.resource_id, verts.as_mut_ptr() as _, 4);
+        });
+    }
+}
 
 // pub unsafe fn Texture_Render(tex: &mut Texture) {
 //     let white = PACKEDCOL_WHITE;
@@ -67,6 +124,59 @@ pub unsafe fn Gfx_Draw2DTexture(tex: &mut Texture, col: PackedCol) {
     });
 }
 
+/// Like Gfx_Draw2DTexture but with float coordinates
+pub unsafe fn Gfx_Draw2DTextureF(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    uv: TextureRec,
+    col: PackedCol,
+) {
+    let (x1, y1, x2, y2) = (x, y, x + width, y + height);
+    let mut vertices = [
+        VertexP3fT2fC4b {
+            X: x1,
+            Y: y1,
+            Z: 0.0,
+            Col: col,
+            U: uv.U1,
+            V: uv.V1,
+        },
+        VertexP3fT2fC4b {
+            X: x2,
+            Y: y1,
+            Z: 0.0,
+            Col: col,
+            U: uv.U2,
+            V: uv.V1,
+        },
+        VertexP3fT2fC4b {
+            X: x2,
+            Y: y2,
+            Z: 0.0,
+            Col: col,
+            U: uv.U2,
+            V: uv.V2,
+        },
+        VertexP3fT2fC4b {
+            X: x1,
+            Y: y2,
+            Z: 0.0,
+            Col: col,
+            U: uv.U1,
+            V: uv.V2,
+        },
+    ];
+
+    Gfx_SetVertexFormat(VertexFormat__VERTEX_FORMAT_P3FT2FC4B);
+    TEX_VB.with(|tex_vb| {
+        let tex_vb = tex_vb.borrow_mut();
+        let tex_vb = tex_vb.as_ref().unwrap();
+        Gfx_UpdateDynamicVb_IndexedTris(tex_vb.resource_id, vertices.as_mut_ptr() as _, 4);
+    });
+}
+
 pub unsafe fn Texture_RenderShaded(tex: &mut Texture, shadeCol: PackedCol) {
     Gfx_BindTexture(tex.ID);
     Gfx_Draw2DTexture(tex, shadeCol);
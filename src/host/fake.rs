@@ -0,0 +1,130 @@
+use super::{Host, PlayerEntity, TabEntry};
+use crate::chat::{self, hidden_communication};
+use classicube_helpers::tab_list::remove_color;
+use classicube_sys::{MsgType_MSG_TYPE_NORMAL, Vec3};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+/// An in-memory game for tests
+///
+/// Records the chat we send and print, and lets tests say things in chat
+/// and add players to the map and tab list.
+pub struct FakeHost {
+    single_player: Cell<bool>,
+    username: String,
+    sent: RefCell<Vec<String>>,
+    added: RefCell<Vec<String>>,
+    players: RefCell<HashMap<u8, PlayerEntity>>,
+    /// nick name, entry
+    tab_list: RefCell<Vec<(String, TabEntry)>>,
+}
+
+impl FakeHost {
+    /// Creates a multiplayer game where we're `username`, and makes it the host
+    pub fn install(username: &str) -> Rc<Self> {
+        let fake = Rc::new(Self {
+            single_player: Cell::new(false),
+            username: username.to_string(),
+            sent: Default::default(),
+            added: Default::default(),
+            players: Default::default(),
+            tab_list: Default::default(),
+        });
+
+        super::set(fake.clone());
+
+        fake
+    }
+
+    pub fn set_single_player(&self, single_player: bool) {
+        self.single_player.set(single_player);
+    }
+
+    /// Adds a player standing at `position` to the map and tab list
+    pub fn add_player(&self, id: u8, nick_name: &str, group: Option<&str>, position: Vec3) {
+        self.players.borrow_mut().insert(
+            id,
+            PlayerEntity {
+                position,
+                eye_position: Vec3 {
+                    X: position.X,
+                    Y: position.Y + 1.625,
+                    Z: position.Z,
+                },
+                head: [0.0, 0.0],
+                rot: [0.0, 0.0, 0.0],
+            },
+        );
+
+        self.tab_list.borrow_mut().push((
+            nick_name.to_string(),
+            TabEntry {
+                id,
+                real_name: remove_color(nick_name).trim().to_string(),
+                group: group.map(|group| group.to_string()),
+            },
+        ));
+    }
+
+    /// They're still in the tab list but not on our map
+    pub fn remove_entity(&self, id: u8) {
+        self.players.borrow_mut().remove(&id);
+    }
+
+    /// Messages sent to the server since the last call
+    pub fn take_sent(&self) -> Vec<String> {
+        self.sent.borrow_mut().drain(..).collect()
+    }
+
+    /// Messages printed to our chat since the last call
+    pub fn take_added(&self) -> Vec<String> {
+        self.added.borrow_mut().drain(..).collect()
+    }
+
+    /// Has the server send us `message`, returns true if it was hidden from chat
+    pub fn receive_chat(&self, message: &str) -> bool {
+        // the chat hook is only installed in multiplayer
+        if !self.single_player.get() && hidden_communication::handle_chat_message(message.into()) {
+            return true;
+        }
+
+        chat::handle_chat_received(message.into(), MsgType_MSG_TYPE_NORMAL);
+
+        false
+    }
+}
+
+impl Host for FakeHost {
+    fn is_single_player(&self) -> bool {
+        self.single_player.get()
+    }
+
+    fn get_username(&self) -> String {
+        self.username.clone()
+    }
+
+    fn send_chat(&self, message: &str) {
+        self.sent.borrow_mut().push(message.to_string());
+    }
+
+    fn add_chat(&self, message: &str) {
+        self.added.borrow_mut().push(message.to_string());
+    }
+
+    fn get_player(&self, id: u8) -> Option<PlayerEntity> {
+        self.players.borrow().get(&id).copied()
+    }
+
+    fn find_tab_entry(&self, nick_name: &str) -> Option<TabEntry> {
+        let nick_name = remove_color(nick_name);
+
+        self.tab_list
+            .borrow()
+            .iter()
+            .find(|(nick, _entry)| remove_color(nick).trim() == nick_name.trim())
+            .map(|(_nick, entry)| entry.clone())
+    }
+}
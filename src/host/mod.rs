@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod fake;
+mod real;
+
+#[cfg(test)]
+pub use self::fake::FakeHost;
+pub use self::real::RealHost;
+use classicube_sys::Vec3;
+use std::{cell::RefCell, rc::Rc};
+
+thread_local!(
+    static HOST: RefCell<Option<Rc<dyn Host>>> = RefCell::new(None);
+);
+
+/// The parts of the game we read and drive outside of rendering
+///
+/// Everything goes through `get()` so tests can swap in `FakeHost`.
+pub trait Host {
+    fn is_single_player(&self) -> bool;
+
+    /// our login name
+    fn get_username(&self) -> String;
+
+    /// sends `message` to the server as if we typed it
+    fn send_chat(&self, message: &str);
+
+    /// shows `message` in our chat only
+    fn add_chat(&self, message: &str);
+
+    /// a player on our map, `ENTITY_SELF_ID` is us
+    fn get_player(&self, id: u8) -> Option<PlayerEntity>;
+
+    /// looks up a player by the nick name shown in chat
+    fn find_tab_entry(&self, nick_name: &str) -> Option<TabEntry>;
+}
+
+#[derive(Clone, Copy)]
+pub struct PlayerEntity {
+    pub position: Vec3,
+    pub eye_position: Vec3,
+    /// pitch, yaw
+    pub head: [f32; 2],
+    /// x, y, z
+    pub rot: [f32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabEntry {
+    pub id: u8,
+    /// without colors
+    pub real_name: String,
+    pub group: Option<String>,
+}
+
+pub fn initialize() {
+    set(Rc::new(RealHost::new()));
+}
+
+pub fn shutdown() {
+    HOST.with(|cell| {
+        cell.borrow_mut().take();
+    });
+}
+
+pub fn set(host: Rc<dyn Host>) {
+    HOST.with(|cell| {
+        *cell.borrow_mut() = Some(host);
+    });
+}
+
+// cloned out so callers can call back into us while using it
+pub fn get() -> Rc<dyn Host> {
+    HOST.with(|cell| cell.borrow().clone().expect("host not initialized"))
+}
@@ -0,0 +1,68 @@
+use super::{Host, PlayerEntity, TabEntry};
+use classicube_helpers::{entities::Entities, tab_list::TabList};
+use classicube_sys::{Chat_Add, Chat_Send, Game_Username, OwnedString, Server};
+
+/// The running game
+pub struct RealHost {
+    tab_list: TabList,
+    entities: Entities,
+}
+
+impl RealHost {
+    pub fn new() -> Self {
+        Self {
+            tab_list: TabList::new(),
+            entities: Entities::new(),
+        }
+    }
+}
+
+impl Host for RealHost {
+    fn is_single_player(&self) -> bool {
+        unsafe { Server.IsSinglePlayer != 0 }
+    }
+
+    fn get_username(&self) -> String {
+        unsafe { Game_Username.to_string() }
+    }
+
+    fn send_chat(&self, message: &str) {
+        let owned_string = OwnedString::new(message);
+
+        unsafe {
+            Chat_Send(owned_string.as_cc_string(), 0);
+        }
+    }
+
+    fn add_chat(&self, message: &str) {
+        let owned_string = OwnedString::new(message);
+
+        unsafe {
+            Chat_Add(owned_string.as_cc_string());
+        }
+    }
+
+    fn get_player(&self, id: u8) -> Option<PlayerEntity> {
+        self.entities.get(id).map(|entity| {
+            let head = entity.get_head();
+            let rot = entity.get_rot();
+
+            PlayerEntity {
+                position: entity.get_position(),
+                eye_position: entity.get_eye_position(),
+                head: [head[0], head[1]],
+                rot: [rot[0], rot[1], rot[2]],
+            }
+        })
+    }
+
+    fn find_tab_entry(&self, nick_name: &str) -> Option<TabEntry> {
+        let entry = self.tab_list.find_entry_by_nick_name(nick_name)?;
+
+        Some(TabEntry {
+            id: entry.get_id(),
+            real_name: entry.get_real_name()?,
+            group: entry.get_group(),
+        })
+    }
+}
@@ -1,11 +1,16 @@
-mod async_manager;
-mod cef;
+// public for the headless tests in tests/
+#[doc(hidden)]
+pub mod async_manager;
+#[doc(hidden)]
+pub mod cef;
 mod chat;
 mod entity_manager;
 mod error;
 mod helpers;
+mod host;
 mod logger;
 mod macros;
+mod metrics;
 mod players;
 mod plugin;
 mod search;
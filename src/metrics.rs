@@ -0,0 +1,224 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    os::raw::c_int,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+// how often the per second rates are recomputed
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// tasks can finish on tokio's threads
+static ACTIVE_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local!(
+    static METRICS: RefCell<Metrics> = RefCell::new(Metrics::default());
+);
+
+/// Events per second, recomputed every RATE_WINDOW
+struct Rate {
+    window_start: Instant,
+    count: usize,
+    per_second: f32,
+}
+
+impl Default for Rate {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            count: 0,
+            per_second: 0.0,
+        }
+    }
+}
+
+impl Rate {
+    fn add(&mut self, count: usize) {
+        self.update();
+        self.count += count;
+    }
+
+    fn get(&mut self) -> f32 {
+        self.update();
+        self.per_second
+    }
+
+    fn update(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+
+        self.per_second = self.count as f32 / elapsed.as_secs_f32();
+        self.window_start = Instant::now();
+        self.count = 0;
+    }
+}
+
+#[derive(Default)]
+struct Timing {
+    count: usize,
+    failed: usize,
+    total: Duration,
+    max: Duration,
+}
+
+impl Timing {
+    fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::from_secs(0)
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+#[derive(Default)]
+struct BrowserMetrics {
+    paints: usize,
+    paint_rate: Rate,
+    upload_bytes: usize,
+    upload_rate: Rate,
+}
+
+#[derive(Default)]
+struct Metrics {
+    step: Timing,
+    js_calls: usize,
+    js_rate: Rate,
+    whispers: Timing,
+    // browser_id, metrics
+    browsers: HashMap<c_int, BrowserMetrics>,
+}
+
+/// Counts a running async task until dropped
+pub struct TaskGuard;
+
+impl TaskGuard {
+    pub fn new() -> Self {
+        ACTIVE_TASKS.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        ACTIVE_TASKS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn with_metrics<F: FnOnce(&mut Metrics)>(f: F) {
+    METRICS.with(|cell| f(&mut *cell.borrow_mut()));
+}
+
+/// One run of the cef message loop
+pub fn record_step(duration: Duration) {
+    with_metrics(|metrics| metrics.step.record(duration));
+}
+
+pub fn record_js_call() {
+    with_metrics(|metrics| {
+        metrics.js_calls += 1;
+        metrics.js_rate.add(1);
+    });
+}
+
+pub fn record_paint(browser_id: c_int) {
+    with_metrics(|metrics| {
+        let browser = metrics.browsers.entry(browser_id).or_default();
+        browser.paints += 1;
+        browser.paint_rate.add(1);
+    });
+}
+
+/// `bytes` of pixels sent to the gpu
+pub fn record_upload(browser_id: c_int, bytes: usize) {
+    with_metrics(|metrics| {
+        let browser = metrics.browsers.entry(browser_id).or_default();
+        browser.upload_bytes += bytes;
+        browser.upload_rate.add(bytes);
+    });
+}
+
+/// How long asking one player for their screens over whispers took
+pub fn record_whisper(duration: Duration, ok: bool) {
+    with_metrics(|metrics| {
+        metrics.whispers.record(duration);
+        if !ok {
+            metrics.whispers.failed += 1;
+        }
+    });
+}
+
+pub fn remove_browser(browser_id: c_int) {
+    with_metrics(|metrics| {
+        metrics.browsers.remove(&browser_id);
+    });
+}
+
+pub fn get_active_tasks() -> usize {
+    ACTIVE_TASKS.load(Ordering::SeqCst)
+}
+
+/// Human readable lines for chat and the hud
+pub fn get_summary() -> Vec<String> {
+    let mut lines = Vec::new();
+
+    METRICS.with(|cell| {
+        let metrics = &mut *cell.borrow_mut();
+
+        lines.push(format!(
+            "step: avg {:.2?}, max {:.2?} ({} runs)",
+            metrics.step.average(),
+            metrics.step.max,
+            metrics.step.count
+        ));
+        lines.push(format!(
+            "js: {:.1}/s ({} total), tasks: {}",
+            metrics.js_rate.get(),
+            metrics.js_calls,
+            get_active_tasks()
+        ));
+        lines.push(format!(
+            "whispers: avg {:.2?}, max {:.2?} ({} sent, {} failed)",
+            metrics.whispers.average(),
+            metrics.whispers.max,
+            metrics.whispers.count,
+            metrics.whispers.failed
+        ));
+
+        let mut browser_ids: Vec<_> = metrics.browsers.keys().cloned().collect();
+        browser_ids.sort();
+        for browser_id in browser_ids {
+            let browser = metrics.browsers.get_mut(&browser_id).unwrap();
+            lines.push(format!(
+                "browser {}: {:.1} paints/s ({} total), {:.2} MB/s ({} MB total)",
+                browser_id,
+                browser.paint_rate.get(),
+                browser.paints,
+                browser.upload_rate.get() / 1024.0 / 1024.0,
+                browser.upload_bytes / 1024 / 1024
+            ));
+        }
+    });
+
+    lines
+}
+
+#[test]
+fn test_timing() {
+    let mut timing = Timing::default();
+    assert_eq!(timing.average(), Duration::from_secs(0));
+
+    timing.record(Duration::from_millis(10));
+    timing.record(Duration::from_millis(30));
+    assert_eq!(timing.average(), Duration::from_millis(20));
+    assert_eq!(timing.max, Duration::from_millis(30));
+}
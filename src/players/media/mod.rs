@@ -1,14 +1,6 @@
-use super::{Player, PlayerTrait, WebPlayer};
-use crate::{
-    async_manager::AsyncManager,
-    cef::RustRefBrowser,
-    chat::{Chat, ENTITIES},
-    entity_manager::EntityManager,
-    error::*,
-};
-use classicube_helpers::{color, OptionWithInner};
-use classicube_sys::ENTITIES_SELF_ID;
-use futures::{future::RemoteHandle, prelude::*};
+use super::{PlayerTrait, WebPlayer};
+use crate::{cef::RustRefBrowser, chat::Chat, error::*};
+use classicube_helpers::color;
 use log::debug;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -30,9 +22,6 @@ pub struct MediaPlayer {
     // 0-1
     pub volume: f32,
 
-    #[serde(skip)]
-    volume_loop_handle: Option<RemoteHandle<()>>,
-
     #[serde(skip)]
     last_title: String,
 }
@@ -44,7 +33,6 @@ impl Default for MediaPlayer {
             time: Duration::from_millis(0),
             volume: 1.0,
             start_time: None,
-            volume_loop_handle: None,
             last_title: String::new(),
         }
     }
@@ -76,14 +64,9 @@ impl PlayerTrait for MediaPlayer {
         }
     }
 
-    fn on_create(&mut self, entity_id: usize) -> String {
+    fn on_create(&mut self, _entity_id: usize) -> String {
         debug!("MediaPlayer on_create {}", self.url);
 
-        let (f, remote_handle) = start_volume_loop(entity_id).remote_handle();
-        self.volume_loop_handle = Some(remote_handle);
-
-        AsyncManager::spawn_local_on_main_thread(f);
-
         format!(
             "data:text/html;base64,{}",
             base64::encode(
@@ -127,47 +110,18 @@ impl PlayerTrait for MediaPlayer {
         let start_time = self.start_time.ok_or("no start time")?;
         Ok(Instant::now() - start_time)
     }
-}
 
-async fn start_volume_loop(entity_id: usize) {
-    loop {
-        AsyncManager::sleep(Duration::from_millis(32)).await;
-
-        let maybe_entity_pos = EntityManager::with_by_entity_id(entity_id, |entity| {
-            if let Player::Media(media) = &entity.player {
-                if media.start_time.is_some() {
-                    // if we're loaded
-
-                    Ok(entity
-                        .browser
-                        .as_ref()
-                        .map(|a| (entity.entity.Position, a.clone())))
-                } else {
-                    Ok(None)
-                }
-            } else {
-                Ok(None)
-            }
-        })
-        .ok()
-        .flatten();
-
-        if let Some((entity_pos, browser)) = maybe_entity_pos {
-            let maybe_my_pos = ENTITIES
-                .with_inner(|entities| {
-                    let me = entities.get(ENTITIES_SELF_ID as _)?;
-
-                    Some(me.get_position())
-                })
-                .flatten();
-
-            if let Some(my_pos) = maybe_my_pos {
-                let percent = (entity_pos - my_pos).length_squared().sqrt() / 30f32;
-                let percent = (1.0 - percent).max(0.0).min(1.0);
-
-                MediaPlayer::set_volume(&browser, percent);
-            }
+    fn set_volume(&mut self, browser: &mut RustRefBrowser, percent: f32) -> Result<()> {
+        if self.start_time.is_none() {
+            bail!("page not loaded");
         }
+
+        // used again if the page reloads
+        self.volume = percent;
+
+        Self::execute_player_method(browser, &format!("volume = {}", percent));
+
+        Ok(())
     }
 }
 
@@ -182,11 +136,6 @@ impl MediaPlayer {
         browser.execute_javascript(code).unwrap();
     }
 
-    /// volume is a float between 0-1
-    fn set_volume(browser: &RustRefBrowser, percent: f32) {
-        Self::execute_player_method(browser, &format!("volume = {}", percent))
-    }
-
     fn seek_to(browser: &RustRefBrowser, seconds: f32) {
         Self::execute_player_method(browser, &format!("currentTime = {}", seconds));
         Self::execute_player_method(browser, "play()");
@@ -1,11 +1,18 @@
 mod media;
+pub mod policy;
 mod web;
 mod youtube;
 
-pub use self::{media::MediaPlayer, web::WebPlayer, youtube::YoutubePlayer};
-use crate::{cef::RustRefBrowser, error::*};
+pub use self::{
+    media::MediaPlayer,
+    web::WebPlayer,
+    youtube::{PlayerState, YoutubePlayer},
+};
+use crate::{cef::RustRefBrowser, chat::Chat, error::*};
+use log::warn;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use url::Url;
 
 pub trait PlayerTrait {
     fn from_input(input: &str) -> Result<Self>
@@ -29,6 +36,11 @@ pub trait PlayerTrait {
     fn set_current_time(&mut self, _browser: &mut RustRefBrowser, _time: Duration) -> Result<()> {
         bail!("setting time not supported");
     }
+
+    /// volume is a float between 0-1
+    fn set_volume(&mut self, _browser: &mut RustRefBrowser, _percent: f32) -> Result<()> {
+        bail!("setting volume not supported");
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,11 +77,20 @@ impl PlayerTrait for Player {
     }
 
     fn on_create(&mut self, entity_id: usize) -> String {
-        match self {
+        let url = match self {
             Player::Youtube(player) => player.on_create(entity_id),
             Player::Media(player) => player.on_create(entity_id),
             Player::Web(player) => player.on_create(entity_id),
+        };
+
+        // every load goes through here, show why instead of loading blocked urls
+        if let Err(e) = self.check_policy() {
+            warn!("blocked {}: {}", self.get_url(), e);
+            Chat::print(format!("cef: blocked {}: {}", self.get_url(), e));
+            return policy::placeholder_url(&self.get_url(), &e.to_string());
         }
+
+        url
     }
 
     fn on_page_loaded(&mut self, browser: &mut RustRefBrowser) {
@@ -103,6 +124,47 @@ impl PlayerTrait for Player {
             Player::Web(player) => player.set_current_time(browser, time),
         }
     }
+
+    fn set_volume(&mut self, browser: &mut RustRefBrowser, percent: f32) -> Result<()> {
+        match self {
+            Player::Youtube(player) => player.set_volume(browser, percent),
+            Player::Media(player) => player.set_volume(browser, percent),
+            Player::Web(player) => player.set_volume(browser, percent),
+        }
+    }
+}
+
+impl Player {
+    /// the url we're showing, before it's wrapped in one of our pages
+    pub fn get_url(&self) -> String {
+        match self {
+            Player::Youtube(player) => format!("https://www.youtube.com/watch?v={}", player.id),
+            Player::Media(player) => player.url.clone(),
+            Player::Web(player) => player.get_url().to_string(),
+        }
+    }
+
+    /// if this shows one of our media pages instead of a normal web page
+    pub fn is_media(&self) -> bool {
+        if let Player::Web(_) = self {
+            false
+        } else {
+            true
+        }
+    }
+
+    pub fn check_policy(&self) -> Result<()> {
+        let url = Url::parse(&self.get_url())?;
+
+        policy::check(&url, self.is_media())
+    }
+
+    /// if we should ask before loading this from another player
+    pub fn needs_confirmation(&self) -> bool {
+        Url::parse(&self.get_url())
+            .map(|url| policy::needs_confirmation(&url))
+            .unwrap_or(true)
+    }
 }
 
 #[test]
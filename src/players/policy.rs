@@ -0,0 +1,501 @@
+use crate::error::*;
+use async_std::net::ToSocketAddrs;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    io::ErrorKind as IoErrorKind,
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, Instant},
+};
+use url::{Host, Url};
+
+// stored next to cef.log
+const SETTINGS_PATH: &str = "cef-policy.json";
+
+// how long we trust what a host name resolved to
+const RESOLVE_TTL: Duration = Duration::from_secs(60);
+
+thread_local!(
+    static POLICY: RefCell<Policy> = RefCell::new(Policy::default());
+);
+
+// host name, when we resolved it, where it went
+thread_local!(
+    static RESOLVED: RefCell<HashMap<String, (Instant, Resolved)>> = RefCell::new(HashMap::new());
+);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Resolved {
+    Public,
+    Private,
+    Failed,
+}
+
+/// What to do with a frame navigating somewhere
+#[derive(Debug, PartialEq)]
+pub enum Navigation {
+    Allow,
+    /// with the reason
+    Block(String),
+    /// ask again after `resolve` finds where this host name goes
+    Resolve(String),
+}
+
+/// Which urls we're willing to load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// if not empty, only hosts matching one of these are loaded
+    pub allow: Vec<String>,
+    /// hosts matching one of these are never loaded
+    pub deny: Vec<String>,
+    /// only load youtube and media files unless the host is allowed
+    pub media_only: bool,
+    /// don't load localhost or LAN addresses
+    pub block_private: bool,
+    /// show a card instead of loading urls from other players until we accept
+    pub ask_first: bool,
+    /// hosts we don't ask about anymore
+    pub accepted: Vec<String>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            media_only: false,
+            block_private: true,
+            ask_first: false,
+            accepted: Vec::new(),
+        }
+    }
+}
+
+impl Policy {
+    /// Err with the reason if `url` shouldn't be loaded
+    ///
+    /// `is_media` if the url is played by one of our media pages
+    /// instead of being loaded as a normal web page.
+    pub fn check(&self, url: &Url, is_media: bool) -> Result<()> {
+        let host = url.host().chain_err(|| "no host")?;
+        let host_name = host.to_string().to_lowercase();
+
+        if self.block_private && is_private_host(&host) {
+            bail!("{} is a private address", host_name);
+        }
+
+        if self.deny.iter().any(|pattern| matches(pattern, &host_name)) {
+            bail!("{} is denied", host_name);
+        }
+
+        let allowed = self
+            .allow
+            .iter()
+            .any(|pattern| matches(pattern, &host_name));
+
+        if !self.allow.is_empty() && !allowed {
+            bail!("{} is not allowed", host_name);
+        }
+
+        if self.media_only && !is_media && !allowed {
+            bail!("only media is allowed");
+        }
+
+        Ok(())
+    }
+
+    /// if we should ask before loading `url` from another player
+    pub fn needs_confirmation(&self, url: &Url) -> bool {
+        if !self.ask_first {
+            return false;
+        }
+
+        let host_name = url
+            .host_str()
+            .map(|host| host.to_lowercase())
+            .unwrap_or_default();
+
+        !self
+            .accepted
+            .iter()
+            .any(|pattern| matches(pattern, &host_name))
+    }
+}
+
+/// `*` matches anything, `*.example.com` also matches `example.com`
+fn matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+
+    if pattern.starts_with("*.") && host == &pattern[2..] {
+        return true;
+    }
+
+    glob_matches(pattern.as_bytes(), host.as_bytes())
+}
+
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| glob_matches(rest, &text[i..])),
+        Some((c, rest)) => text.first() == Some(c) && glob_matches(rest, &text[1..]),
+    }
+}
+
+fn is_private_host(host: &Host<&str>) -> bool {
+    match host {
+        Host::Domain(domain) => {
+            let domain = domain.to_lowercase();
+            let domain = domain.trim_end_matches('.');
+
+            domain == "localhost"
+                || domain.ends_with(".localhost")
+                || domain.ends_with(".local")
+                || domain.ends_with(".lan")
+                || domain.ends_with(".internal")
+        }
+        Host::Ipv4(ip) => is_private_ip(&IpAddr::V4(*ip)),
+        Host::Ipv6(ip) => is_private_ip(&IpAddr::V6(*ip)),
+    }
+}
+
+fn is_private_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(ipv4) = ip.to_ipv4() {
+                if is_private_ipv4(&ipv4) {
+                    return true;
+                }
+            }
+
+            let first = ip.segments()[0];
+
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // fc00::/7 unique local
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10 link local
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+fn is_private_ipv4(ip: &Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // 100.64.0.0/10 carrier-grade NAT
+        || (a == 100 && (b & 0xc0) == 64)
+}
+
+pub fn initialize() {
+    match load() {
+        Ok(policy) => {
+            POLICY.with(|cell| *cell.borrow_mut() = policy);
+        }
+
+        Err(e) => {
+            warn!("couldn't load {}: {}", SETTINGS_PATH, e);
+        }
+    }
+}
+
+fn load() -> Result<Policy> {
+    match fs::read_to_string(SETTINGS_PATH) {
+        Ok(data) => Ok(serde_json::from_str(&data)?),
+        Err(e) if e.kind() == IoErrorKind::NotFound => Ok(Policy::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save(policy: &Policy) -> Result<()> {
+    let data = serde_json::to_string_pretty(policy)?;
+    fs::write(SETTINGS_PATH, data)?;
+
+    Ok(())
+}
+
+pub fn check(url: &Url, is_media: bool) -> Result<()> {
+    POLICY.with(|cell| cell.borrow().check(url, is_media))
+}
+
+/// Checks every page load and redirect, not just the url we were given
+///
+/// Host names are looked up so they can't point us at a private address.
+pub fn check_navigation(url: &str, is_media: bool) -> Navigation {
+    let url = match parse_web_url(url) {
+        Ok(Some(url)) => url,
+        Ok(None) => return Navigation::Allow,
+        Err(e) => return Navigation::Block(e.to_string()),
+    };
+
+    POLICY.with(|cell| {
+        let policy = &*cell.borrow();
+
+        if let Err(e) = policy.check(&url, is_media) {
+            return Navigation::Block(e.to_string());
+        }
+
+        check_private(policy, &url)
+    })
+}
+
+/// Checks every request a page makes, like frames, images and scripts
+///
+/// Only private addresses are blocked here, pages are free to use other
+/// hosts than the one they were loaded from.
+pub fn check_request(url: &str) -> Navigation {
+    let url = match parse_web_url(url) {
+        Ok(Some(url)) => url,
+        Ok(None) => return Navigation::Allow,
+        Err(e) => return Navigation::Block(e.to_string()),
+    };
+
+    POLICY.with(|cell| check_private(&cell.borrow(), &url))
+}
+
+/// None for urls that don't go over the network,
+/// like our own pages and placeholders which are data urls
+fn parse_web_url(url: &str) -> Result<Option<Url>> {
+    let url = Url::parse(url)?;
+
+    match url.scheme() {
+        "http" | "https" | "ws" | "wss" => Ok(Some(url)),
+        _ => Ok(None),
+    }
+}
+
+fn check_private(policy: &Policy, url: &Url) -> Navigation {
+    if !policy.block_private {
+        return Navigation::Allow;
+    }
+
+    let host = match url.host() {
+        Some(host) if is_private_host(&host) => {
+            return Navigation::Block(format!("{} is a private address", host));
+        }
+        Some(Host::Domain(domain)) => domain.to_lowercase(),
+        _ => return Navigation::Allow,
+    };
+
+    match get_resolved(&host) {
+        Some(Resolved::Public) => Navigation::Allow,
+        Some(Resolved::Private) => Navigation::Block(format!("{} is a private address", host)),
+        Some(Resolved::Failed) => Navigation::Block(format!("couldn't resolve {}", host)),
+        None => Navigation::Resolve(host),
+    }
+}
+
+fn get_resolved(host: &str) -> Option<Resolved> {
+    RESOLVED.with(|cell| {
+        let resolved = &mut *cell.borrow_mut();
+        resolved.retain(|_, (time, _)| time.elapsed() < RESOLVE_TTL);
+
+        resolved.get(host).map(|(_, resolved)| *resolved)
+    })
+}
+
+/// Looks up where `host` goes for `check_navigation` and `check_request`
+pub async fn resolve(host: &str) {
+    let resolved = match (host, 80).to_socket_addrs().await {
+        Ok(addrs) => {
+            let ips: Vec<IpAddr> = addrs.map(|addr| addr.ip()).collect();
+
+            if ips.is_empty() {
+                Resolved::Failed
+            } else if ips.iter().any(is_private_ip) {
+                Resolved::Private
+            } else {
+                Resolved::Public
+            }
+        }
+
+        Err(e) => {
+            warn!("couldn't resolve {}: {}", host, e);
+            Resolved::Failed
+        }
+    };
+
+    RESOLVED.with(|cell| {
+        cell.borrow_mut()
+            .insert(host.to_string(), (Instant::now(), resolved));
+    });
+}
+
+pub fn needs_confirmation(url: &Url) -> bool {
+    POLICY.with(|cell| cell.borrow().needs_confirmation(url))
+}
+
+pub fn get() -> Policy {
+    POLICY.with(|cell| cell.borrow().clone())
+}
+
+/// Changes the policy and saves it
+pub fn update<F>(f: F) -> Result<()>
+where
+    F: FnOnce(&mut Policy),
+{
+    POLICY.with(|cell| {
+        let policy = &mut *cell.borrow_mut();
+        f(policy);
+        save(policy)
+    })
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Page shown instead of a blocked url
+pub fn placeholder_url(url: &str, reason: &str) -> String {
+    let html = format!(
+        r#"<html><body style="background:#222;color:#ccc;font-family:sans-serif;text-align:center;padding-top:20%">
+<h1>Blocked</h1><p>{}</p><p style="color:#888">{}</p></body></html>"#,
+        escape_html(url),
+        escape_html(reason)
+    );
+
+    format!("data:text/html;base64,{}", base64::encode(html))
+}
+
+/// Page shown until we accept a url from another player
+pub fn confirm_url(url: &str, requester: &str) -> String {
+    let html = format!(
+        r#"<html><body style="background:#222;color:#ccc;font-family:sans-serif;text-align:center;padding-top:20%">
+<h1>{} wants to load</h1><p>{}</p>
+<p style="color:#888">click or type "cef accept" to load, "cef accept remember" to always allow this site</p></body></html>"#,
+        escape_html(requester),
+        escape_html(url)
+    );
+
+    format!("data:text/html;base64,{}", base64::encode(html))
+}
+
+#[test]
+fn test_policy() {
+    let check = |policy: &Policy, url: &str, is_media: bool| {
+        policy.check(&Url::parse(url).unwrap(), is_media).is_ok()
+    };
+
+    let mut policy = Policy::default();
+    assert!(check(&policy, "https://www.classicube.net/", false));
+    assert!(!check(&policy, "http://localhost:8080/", false));
+    assert!(!check(&policy, "http://127.0.0.1/", false));
+    assert!(!check(&policy, "http://192.168.1.1/", false));
+    assert!(!check(&policy, "http://10.0.0.5/", false));
+    assert!(!check(&policy, "http://[::1]/", false));
+    assert!(!check(&policy, "http://[fd00::1]/", false));
+    assert!(!check(&policy, "http://printer.local/", false));
+    assert!(check(&policy, "http://8.8.8.8/", false));
+
+    policy.deny.push("*.example.com".to_string());
+    assert!(!check(&policy, "https://example.com/", false));
+    assert!(!check(&policy, "https://www.example.com/", false));
+    assert!(check(&policy, "https://example.org/", false));
+
+    policy.allow.push("*.youtube.com".to_string());
+    assert!(check(&policy, "https://www.youtube.com/watch?v=a", true));
+    assert!(!check(&policy, "https://example.org/", false));
+
+    policy.allow.clear();
+    policy.media_only = true;
+    assert!(check(&policy, "https://example.org/video.mp4", true));
+    assert!(!check(&policy, "https://example.org/", false));
+
+    policy.block_private = false;
+    policy.media_only = false;
+    assert!(check(&policy, "http://192.168.1.1/", false));
+}
+
+#[test]
+fn test_check_navigation() {
+    let resolved = |host: &str, resolved: Resolved| {
+        RESOLVED.with(|cell| {
+            cell.borrow_mut()
+                .insert(host.to_string(), (Instant::now(), resolved));
+        });
+    };
+
+    assert_eq!(
+        check_navigation("data:text/html,hi", false),
+        Navigation::Allow
+    );
+    assert_eq!(
+        check_navigation("http://8.8.8.8/", false),
+        Navigation::Allow
+    );
+    assert!(matches!(
+        check_navigation("http://127.0.0.1/", false),
+        Navigation::Block(_)
+    ));
+
+    // names are looked up before they're loaded
+    assert_eq!(
+        check_navigation("https://Example.com/a", false),
+        Navigation::Resolve("example.com".to_string())
+    );
+    resolved("example.com", Resolved::Public);
+    assert_eq!(
+        check_navigation("https://example.com/a", false),
+        Navigation::Allow
+    );
+
+    resolved("rebind.example.org", Resolved::Private);
+    assert!(matches!(
+        check_navigation("https://rebind.example.org/", false),
+        Navigation::Block(_)
+    ));
+
+    // requests skip the allow list and media only, but not private addresses
+    assert_eq!(
+        check_request("https://cdn.example.net/a.js"),
+        Navigation::Resolve("cdn.example.net".to_string())
+    );
+    assert!(matches!(
+        check_request("http://192.168.1.1/"),
+        Navigation::Block(_)
+    ));
+    assert!(matches!(
+        check_request("https://rebind.example.org/img.png"),
+        Navigation::Block(_)
+    ));
+    assert_eq!(check_request("data:image/png,"), Navigation::Allow);
+}
+
+#[test]
+fn test_needs_confirmation() {
+    let url = Url::parse("https://www.youtube.com/watch?v=a").unwrap();
+
+    let mut policy = Policy::default();
+    assert!(!policy.needs_confirmation(&url));
+
+    policy.ask_first = true;
+    assert!(policy.needs_confirmation(&url));
+
+    policy.accepted.push("*.youtube.com".to_string());
+    assert!(!policy.needs_confirmation(&url));
+    assert!(policy.needs_confirmation(&Url::parse("https://example.com/").unwrap()));
+}
+
+#[test]
+fn test_matches() {
+    assert!(matches("*", "anything.com"));
+    assert!(matches("google.com", "google.com"));
+    assert!(!matches("google.com", "www.google.com"));
+    assert!(matches("*.google.com", "www.google.com"));
+    assert!(matches("*.google.com", "google.com"));
+    assert!(!matches("*.google.com", "notgoogle.com"));
+    assert!(matches("cdn*.example.com", "cdn2.example.com"));
+    assert!(matches("GOOGLE.com", "google.com"));
+}
@@ -56,6 +56,10 @@ impl PlayerTrait for WebPlayer {
 }
 
 impl WebPlayer {
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+
     pub fn from_url(url: Url) -> Option<Self> {
         let has_tld = url
             .host()
@@ -63,7 +67,7 @@ impl WebPlayer {
                 if let url::Host::Domain(s) = host {
                     s.contains('.')
                 } else {
-                    // allow direct ips
+                    // direct ips are allowed, private ones are blocked by our policy
                     true
                 }
             })
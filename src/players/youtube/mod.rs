@@ -1,17 +1,9 @@
-use super::{Player, PlayerTrait};
-use crate::{
-    async_manager::AsyncManager,
-    cef::RustRefBrowser,
-    chat::{Chat, ENTITIES},
-    entity_manager::EntityManager,
-    error::*,
-};
-use classicube_helpers::{color, OptionWithInner};
-use classicube_sys::ENTITIES_SELF_ID;
-use futures::{future::RemoteHandle, prelude::*};
+use super::PlayerTrait;
+use crate::{cef::RustRefBrowser, chat::Chat, error::*};
+use classicube_helpers::color;
 use log::debug;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     collections::HashMap,
     time::{Duration, Instant},
@@ -31,9 +23,6 @@ pub struct YoutubePlayer {
     // 0-1
     pub volume: f32,
 
-    #[serde(skip)]
-    volume_loop_handle: Option<RemoteHandle<()>>,
-
     #[serde(skip)]
     last_title: String,
 }
@@ -45,7 +34,6 @@ impl Default for YoutubePlayer {
             time: Duration::from_millis(0),
             volume: 1.0,
             start_time: None,
-            volume_loop_handle: None,
             last_title: String::new(),
         }
     }
@@ -86,14 +74,9 @@ impl PlayerTrait for YoutubePlayer {
         }
     }
 
-    fn on_create(&mut self, entity_id: usize) -> String {
+    fn on_create(&mut self, _entity_id: usize) -> String {
         debug!("YoutubePlayer on_create {}", self.id);
 
-        let (f, remote_handle) = start_volume_loop(entity_id).remote_handle();
-        self.volume_loop_handle = Some(remote_handle);
-
-        AsyncManager::spawn_local_on_main_thread(f);
-
         format!(
             "data:text/html;base64,{}",
             base64::encode(
@@ -140,51 +123,84 @@ impl PlayerTrait for YoutubePlayer {
         let start_time = self.start_time.ok_or("no start time")?;
         Ok(Instant::now() - start_time)
     }
-}
 
-async fn start_volume_loop(entity_id: usize) {
-    loop {
-        AsyncManager::sleep(Duration::from_millis(32)).await;
-
-        let maybe_entity_pos = EntityManager::with_by_entity_id(entity_id, |entity| {
-            if let Player::Youtube(yt) = &entity.player {
-                if yt.start_time.is_some() {
-                    // if we're loaded
-
-                    Ok(entity
-                        .browser
-                        .as_ref()
-                        .map(|a| (entity.entity.Position, a.clone())))
-                } else {
-                    Ok(None)
-                }
-            } else {
-                Ok(None)
-            }
-        })
-        .ok()
-        .flatten();
+    fn set_volume(&mut self, browser: &mut RustRefBrowser, percent: f32) -> Result<()> {
+        if self.start_time.is_none() {
+            bail!("page not loaded");
+        }
 
-        if let Some((entity_pos, browser)) = maybe_entity_pos {
-            let maybe_my_pos = ENTITIES
-                .with_inner(|entities| {
-                    let me = entities.get(ENTITIES_SELF_ID as _)?;
+        // the scheduler only sends changes, so a page that reloads or a
+        // player that isn't ready yet has to start at this volume
+        self.volume = percent;
 
-                    Some(me.get_position())
-                })
-                .flatten();
+        let percent = (percent * 100f32) as u32;
+        browser.execute_javascript(format!("startVolume = {};", percent))?;
+        Self::execute_player_method(browser, &format!("setVolume({})", percent));
 
-            if let Some(my_pos) = maybe_my_pos {
-                let percent = (entity_pos - my_pos).length_squared().sqrt() / 30f32;
-                let percent = (1.0 - percent).max(0.0).min(1.0);
+        Ok(())
+    }
+}
 
-                YoutubePlayer::set_volume(&browser, percent);
-            }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerState {
+    Unstarted,
+    Ended,
+    Playing,
+    Paused,
+    Buffering,
+    VideoCued,
+}
+
+impl PlayerState {
+    fn from_i32(state: i32) -> Option<Self> {
+        match state {
+            -1 => Some(PlayerState::Unstarted),
+            0 => Some(PlayerState::Ended),
+            1 => Some(PlayerState::Playing),
+            2 => Some(PlayerState::Paused),
+            3 => Some(PlayerState::Buffering),
+            5 => Some(PlayerState::VideoCued),
+            _ => None,
         }
     }
 }
 
 impl YoutubePlayer {
+    async fn eval_player_getter<T: DeserializeOwned>(
+        browser: &RustRefBrowser,
+        method: &str,
+    ) -> Result<T> {
+        let code = format!(
+            r#"if (
+            typeof window.player === "undefined" ||
+                typeof window.player.{0} === "undefined"
+            ) {{
+                throw new Error("player not ready");
+            }}
+            window.player.{0}();"#,
+            method
+        );
+
+        browser.eval(&code).await
+    }
+
+    /// Asks the page for the video's current time
+    pub async fn real_current_time(browser: &RustRefBrowser) -> Result<Duration> {
+        let seconds: f64 = Self::eval_player_getter(browser, "getCurrentTime").await?;
+        seconds_to_duration(seconds)
+    }
+
+    /// Asks the page for the video's total duration
+    pub async fn real_duration(browser: &RustRefBrowser) -> Result<Duration> {
+        let seconds: f64 = Self::eval_player_getter(browser, "getDuration").await?;
+        seconds_to_duration(seconds)
+    }
+
+    pub async fn player_state(browser: &RustRefBrowser) -> Result<PlayerState> {
+        let state: i32 = Self::eval_player_getter(browser, "getPlayerState").await?;
+        PlayerState::from_i32(state).chain_err(|| format!("unknown player state {}", state))
+    }
+
     fn execute_player_method(browser: &RustRefBrowser, method_with_args: &str) {
         let code = format!(
             r#"if (
@@ -198,13 +214,6 @@ impl YoutubePlayer {
         browser.execute_javascript(code).unwrap();
     }
 
-    /// volume is a float between 0-1
-    fn set_volume(browser: &RustRefBrowser, percent: f32) {
-        let percent = (percent * 100f32) as u32;
-
-        Self::execute_player_method(browser, &format!("setVolume({})", percent))
-    }
-
     fn seek_to(browser: &RustRefBrowser, seconds: u64) {
         // We recommend that you set this parameter to false while the user drags the
         // mouse along a video progress bar and then set it to true when the user releases
@@ -214,6 +223,15 @@ impl YoutubePlayer {
     }
 }
 
+// Duration::from_secs_f64 panics on NaN, infinite or huge values
+fn seconds_to_duration(seconds: f64) -> Result<Duration> {
+    if !seconds.is_finite() || seconds > u32::MAX as f64 {
+        bail!("bad time {}", seconds);
+    }
+
+    Ok(Duration::from_secs_f64(seconds.max(0.0)))
+}
+
 impl YoutubePlayer {
     pub fn from_id(id: String) -> Option<Self> {
         let regex = Regex::new(r"^[A-Za-z0-9_\-]{11}$").unwrap();
@@ -1,4 +1,6 @@
-use crate::{async_manager::AsyncManager, cef::Cef, chat::Chat, entity_manager::EntityManager};
+use crate::{
+    async_manager::AsyncManager, cef::Cef, chat::Chat, entity_manager::EntityManager, host,
+};
 use classicube_helpers::OptionWithInner;
 use classicube_sys::{Server, String_AppendConst};
 use log::debug;
@@ -25,6 +27,8 @@ impl Plugin {
         PLUGIN.with(|cell| {
             assert!(cell.borrow().is_none());
 
+            host::initialize();
+
             Chat::print(format!("Cef v{} initializing", env!("CARGO_PKG_VERSION")));
 
             let append_app_name = CString::new(format!(" +{}", APP_NAME)).unwrap();
@@ -93,6 +97,8 @@ impl Plugin {
 
             // this will run all remaining tasks to completion
             plugin.async_manager.shutdown();
+
+            host::shutdown();
         });
     }
 }
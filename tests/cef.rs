@@ -1,78 +1,167 @@
-// #[test]
-// fn test_cef() {
-//     use crate::cef::AsyncManager;
-
-//     crate::logger::initialize(true, false);
-
-//     unsafe {
-//         extern "C" fn ag(_: *mut classicube_sys::ScheduledTask) {}
-//         classicube_sys::Server.Tick = Some(ag);
-//     }
-
-//     let mut am = AsyncManager::new();
-//     am.initialize();
-
-//     let is_shutdown = std::rc::Rc::new(std::cell::Cell::new(false));
-//     let is_initialized = std::rc::Rc::new(std::cell::Cell::new(false));
-
-//     {
-//         let is_shutdown = is_shutdown.clone();
-//         let is_initialized = is_initialized.clone();
-//         AsyncManager::spawn_local_on_main_thread(async move {
-//             let app = {
-//                 debug!("create");
-//                 let (app, client) = create_app().await;
-//                 is_initialized.set(true);
-
-//                 // {
-//                 //     let is_shutdown = is_shutdown.clone();
-//                 //     AsyncManager::spawn_local_on_main_thread(async move {
-//                 //         while !is_shutdown.get() {
-//                 //             debug!(".");
-//                 //             RustRefApp::step().unwrap();
-//                 //             // tokio::task::yield_now().await;
-//                 //             async_std::task::yield_now().await;
-//                 //         }
-//                 //     });
-//                 // }
-
-//                 debug!("create_browser 1");
-//                 let browser_1 = create_browser(&client, "https://icanhazip.com/".to_string());
-//                 debug!("create_browser 2");
-//                 let browser_2 = create_browser(&client, "https://icanhazip.com/".to_string());
-
-//                 let (browser_1, browser_2) = futures::future::join(browser_1, browser_2).await;
-
-//                 let never = futures::future::pending::<()>();
-//                 let dur = std::time::Duration::from_secs(2);
-//                 assert!(async_std::future::timeout(dur, never).await.is_err());
-
-//                 debug!("browsers close");
-//                 futures::future::join_all(
-//                     [browser_1, browser_2]
-//                         .iter()
-//                         .map(|browser| close_browser(browser)),
-//                 )
-//                 .await;
-//                 // close_browser(&browser_1).await;
-//                 // close_browser(&browser_2).await;
-
-//                 app
-//             };
-
-//             debug!("shutdown");
-//             app.shutdown().unwrap();
-//             is_shutdown.set(true);
-//         });
-//     }
-
-//     while !is_shutdown.get() {
-//         let before = std::time::Instant::now();
-//         AsyncManager::step();
-//         if is_initialized.get() && !is_shutdown.get() {
-//             RustRefApp::step().unwrap();
-//         }
-//         debug!("step {:?}", std::time::Instant::now() - before);
-//         std::thread::sleep(std::time::Duration::from_millis(100));
-//     }
-// }
+//! Runs the real cef interface without the game.
+//!
+//! Ignored by default since it needs the cef runtime next to where the test
+//! runs, which for cargo is this crate's folder. Copy in:
+//!
+//! - all files in `cef_interface/cef_binary/Debug/`
+//! - all files in `cef_interface/cef_binary/Resources/`
+//! - `target/debug/build/classicube-cef-plugin-*/out/cefsimple`
+//!
+//! then run `LD_LIBRARY_PATH=. cargo test --test cef -- --ignored`
+//!
+//! There's no GPU, the game's symbols are stubs from `stubs`, and we step
+//! `AsyncManager` ourselves instead of the game's tick doing it.
+//! Cef can only be initialized once per process so everything runs
+//! inside one test.
+
+mod stubs;
+
+use async_std::future::timeout;
+use classicube_cef_plugin::{
+    async_manager::AsyncManager,
+    cef::{Capture, Cef, CefEvent, RustRefBrowser, CEF_DEFAULT_HEIGHT, CEF_DEFAULT_WIDTH},
+};
+use std::{env, os::raw::c_int, time::Duration};
+use tokio::sync::broadcast;
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Waits for the first event `f` returns Some for
+async fn wait_for_event<T, F>(event_receiver: &mut broadcast::Receiver<CefEvent>, f: F) -> T
+where
+    F: Fn(CefEvent) -> Option<T>,
+{
+    timeout(EVENT_TIMEOUT, async {
+        loop {
+            let event = event_receiver.recv().await.unwrap();
+            if let Some(value) = f(event) {
+                return value;
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for event")
+}
+
+/// Creates a browser and waits for its page to load
+async fn create_loaded_browser(url: &str) -> RustRefBrowser {
+    let mut event_receiver = Cef::create_event_listener();
+
+    let browser = Cef::create_browser(url).await.unwrap();
+    let browser_id = browser.get_identifier();
+
+    wait_for_event(&mut event_receiver, |event| match event {
+        CefEvent::BrowserPageLoaded(browser) if browser.get_identifier() == browser_id => Some(()),
+        _ => None,
+    })
+    .await;
+
+    browser
+}
+
+async fn test_create_close() {
+    let browser = Cef::create_browser("data:text/html,hello").await.unwrap();
+    let browser_id = browser.get_identifier();
+    assert!(browser_id > 0);
+
+    Cef::close_browser(&browser).await.unwrap();
+
+    // ids aren't reused
+    let browser = Cef::create_browser("data:text/html,hello").await.unwrap();
+    assert_ne!(browser.get_identifier(), browser_id);
+    Cef::close_browser(&browser).await.unwrap();
+}
+
+async fn test_paint() {
+    let browser =
+        create_loaded_browser("data:text/html,<body style='margin:0;background:%23ff0000'>").await;
+
+    let Capture {
+        pixels,
+        width,
+        height,
+    } = browser.capture().await.unwrap();
+    assert_eq!(width as c_int, CEF_DEFAULT_WIDTH);
+    assert_eq!(height as c_int, CEF_DEFAULT_HEIGHT);
+    assert_eq!(pixels.len(), 4 * width * height);
+
+    let center = 4 * ((height / 2) * width + width / 2);
+    assert_eq!(&pixels[center..(center + 4)], &[255, 0, 0, 255]);
+
+    Cef::close_browser(&browser).await.unwrap();
+}
+
+async fn test_events() {
+    let mut event_receiver = Cef::create_event_listener();
+
+    let browser = Cef::create_browser("data:text/html,<title>hello title</title>")
+        .await
+        .unwrap();
+    let browser_id = browser.get_identifier();
+
+    let title = wait_for_event(&mut event_receiver, |event| match event {
+        CefEvent::BrowserTitleChange(browser, title) if browser.get_identifier() == browser_id => {
+            Some(title)
+        }
+        _ => None,
+    })
+    .await;
+    assert_eq!(title, "hello title");
+
+    wait_for_event(&mut event_receiver, |event| match event {
+        CefEvent::BrowserPageLoaded(browser) if browser.get_identifier() == browser_id => Some(()),
+        _ => None,
+    })
+    .await;
+
+    Cef::close_browser(&browser).await.unwrap();
+
+    wait_for_event(&mut event_receiver, |event| match event {
+        CefEvent::BrowserClosed(browser) if browser.get_identifier() == browser_id => Some(()),
+        _ => None,
+    })
+    .await;
+}
+
+async fn test_javascript() {
+    let browser = create_loaded_browser("data:text/html,<title>js</title>").await;
+
+    let sum: i32 = browser.eval("1 + 2").await.unwrap();
+    assert_eq!(sum, 3);
+
+    let title: String = browser.eval("document.title").await.unwrap();
+    assert_eq!(title, "js");
+
+    let resolved: String = browser
+        .eval("new Promise((resolve) => setTimeout(() => resolve('later'), 10))")
+        .await
+        .unwrap();
+    assert_eq!(resolved, "later");
+
+    assert!(browser.eval::<()>("throw new Error('oops')").await.is_err());
+
+    Cef::close_browser(&browser).await.unwrap();
+}
+
+#[test]
+#[ignore = "needs the cef runtime, see the top of this file"]
+fn test_cef() {
+    env::set_var("CEF_HEADLESS", "1");
+    stubs::initialize();
+
+    let mut async_manager = AsyncManager::new();
+    async_manager.initialize();
+
+    AsyncManager::block_on_local(async {
+        Cef::initialize().await;
+
+        test_create_close().await;
+        test_paint().await;
+        test_events().await;
+        test_javascript().await;
+
+        Cef::shutdown().await;
+    });
+
+    async_manager.shutdown();
+}
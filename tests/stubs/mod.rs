@@ -0,0 +1,61 @@
+//! The game's symbols for running our code without the game
+//!
+//! Only what the tests reach gets linked, so only that needs a stub here.
+//! If linking fails with an undefined reference to a game symbol, add it.
+
+use classicube_sys::{_ServerConnectionData, ScheduledTask, String as CcString};
+use std::mem;
+
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static mut Server: _ServerConnectionData = unsafe { mem::zeroed() };
+
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static mut Game_Username: CcString = unsafe { mem::zeroed() };
+
+/// Functions that only make sense with the game running
+macro_rules! unavailable {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[no_mangle]
+            #[allow(non_snake_case)]
+            pub extern "C" fn $name() {
+                panic!(concat!(stringify!($name), " isn't available without the game"));
+            }
+        )*
+    };
+}
+
+unavailable!(
+    Chat_Add,
+    Chat_AddOf,
+    Chat_Send,
+    ChatScreen_OpenInput,
+    Drawer2D_MakeFont,
+    Drawer2D_MakeTextTexture,
+    Entity_Init,
+    Entity_SetModel,
+    Font_Free,
+    Gfx_BindTexture,
+    Gfx_DeleteTexture,
+    Gfx_SetAlphaBlending,
+    Gfx_SetAlphaTest,
+    Gfx_SetTexturing,
+    Gfx_UpdateTexturePart,
+    Gui_Add,
+    Gui_Remove,
+    Model_Init,
+    Model_Register,
+    Model_Render,
+    String_AppendConst,
+);
+
+extern "C" fn tick(_task: *mut ScheduledTask) {}
+
+/// Gives `AsyncManager` a tick to hook, we step it ourselves
+pub fn initialize() {
+    unsafe {
+        Server.Tick = Some(tick);
+    }
+}